@@ -0,0 +1,141 @@
+//! Command line parsing.
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use std::path::PathBuf;
+
+use keysim::input::DEFAULT_INPUT;
+use keysim::sim::DEFAULT_DEPTH;
+
+pub const HELP: &str = "\
+keysim - keypad simulator for Advent of Code 2024 day 21
+
+Usage: keysim [COMMAND] [OPTIONS] [INPUT]
+
+Commands:
+  tui          interactive simulator (default)
+  solve        print a shortest sequence for every code
+  complexity   print length and complexity of every code and their sum
+  trace        print the events of one sequence as JSON Lines
+  simulate     run every sequence, or the one given with --line, and print
+               output, presses per pad, final arm positions and faults, exit
+               status 1 if a robot panics or a code cannot be solved
+  check        run the sequences with an expected result after =>, exit
+               status 1 if one does not give it
+
+Arguments:
+  [INPUT]      input file, - reads standard input [default: keypad2024input.txt]
+
+Options:
+  -l, --line N       select sequence N, counting from 1 [default: 1]
+  -p, --pos N        start with the cursor after N presses [default: 0]
+  -d, --depth N      robot pads between manual pad and num pad [default: 2]
+      --layout FILE  read keypad layouts from FILE
+  -c, --code CODE    solve CODE instead of the codes in the input, repeatable
+      --arrows       print sequences with ^ v < > instead of U D L R
+      --json         simulate prints one JSON object per sequence
+  -h, --help         print this help
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Tui,
+    Solve,
+    Complexity,
+    Trace,
+    Simulate,
+    Check,
+    Help,
+}
+
+#[derive(Clone, Debug)]
+pub struct Args {
+    pub command: Command,
+    pub input: PathBuf,
+    /// Zero based index of the selected sequence, if one was given.
+    pub line: Option<usize>,
+    pub pos: usize,
+    pub depth: usize,
+    pub layout: Option<PathBuf>,
+    pub codes: Vec<String>,
+    pub arrows: bool,
+    pub json: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            command: Command::Tui,
+            input: PathBuf::from(DEFAULT_INPUT),
+            line: None,
+            pos: 0,
+            depth: DEFAULT_DEPTH,
+            layout: None,
+            codes: vec![],
+            arrows: false,
+            json: false,
+        }
+    }
+}
+
+/// Parse the arguments after the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args> {
+    let mut parsed = Args::default();
+    let mut input = None;
+    let mut iter = args.into_iter().peekable();
+    if let Some(command) = iter.peek().and_then(|a| command(a)) {
+        parsed.command = command;
+        iter.next();
+    }
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| eyre!("{name} needs a value, see keysim --help"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => parsed.command = Command::Help,
+            "-l" | "--line" => {
+                let line: usize = number(&arg, &value(&arg)?)?;
+                if line == 0 {
+                    bail!("{arg} counts from 1");
+                }
+                parsed.line = Some(line - 1);
+            }
+            "-p" | "--pos" => parsed.pos = number(&arg, &value(&arg)?)?,
+            "-d" | "--depth" => parsed.depth = number(&arg, &value(&arg)?)?,
+            "--layout" => parsed.layout = Some(PathBuf::from(value(&arg)?)),
+            "-c" | "--code" => parsed.codes.push(value(&arg)?.to_uppercase()),
+            "--arrows" => parsed.arrows = true,
+            "--json" => parsed.json = true,
+            a if a.starts_with('-') && a != "-" => bail!("unknown option {a}, see keysim --help"),
+            a => {
+                if input.is_some() {
+                    bail!("more than one input file given, see keysim --help");
+                }
+                input = Some(PathBuf::from(a));
+            }
+        }
+    }
+    if let Some(input) = input {
+        parsed.input = input;
+    }
+    Ok(parsed)
+}
+
+fn command(arg: &str) -> Option<Command> {
+    match arg {
+        "tui" => Some(Command::Tui),
+        "solve" => Some(Command::Solve),
+        "complexity" => Some(Command::Complexity),
+        "trace" => Some(Command::Trace),
+        "simulate" => Some(Command::Simulate),
+        "check" => Some(Command::Check),
+        "help" => Some(Command::Help),
+        _ => None,
+    }
+}
+
+fn number(name: &str, value: &str) -> Result<usize> {
+    value
+        .parse()
+        .wrap_err_with(|| format!("{name} needs a number, got {value}"))
+}
@@ -0,0 +1,270 @@
+//! Subcommands that print to standard output instead of starting the TUI.
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use std::io::{self, BufWriter, Write};
+
+use keysim::input::Input;
+use keysim::keypad::{display_label, ChainLayout};
+use keysim::sim::Simulator;
+use keysim::solver::Solver;
+use keysim::trace::{json_string, presses_by_pad, write_jsonl, Event};
+
+use crate::cli::Args;
+use crate::{fault_report, pad_name};
+
+/// Codes given with `--code`, or else the codes of the input file.
+fn codes<'a>(args: &'a Args, input: &'a Input) -> Result<Vec<&'a str>> {
+    let codes: Vec<&str> = if args.codes.is_empty() {
+        input.codes.iter().map(|(_, code)| code.as_str()).collect()
+    } else {
+        args.codes.iter().map(String::as_str).collect()
+    };
+    if codes.is_empty() {
+        bail!("no numeric codes like 029A in {}", args.input.display());
+    }
+    Ok(codes)
+}
+
+/// Print a shortest sequence for every code.
+pub fn solve(args: &Args, layout: &ChainLayout, input: &Input) -> Result<()> {
+    let mut solver = Solver::new(layout, args.depth);
+    let mut out = BufWriter::new(io::stdout().lock());
+    for code in codes(args, input)? {
+        let mut sequence = solver.solve(code)?;
+        if args.arrows {
+            sequence = sequence.chars().map(display_label).collect();
+        }
+        writeln!(out, "{code}: {sequence}")?;
+    }
+    Ok(out.flush()?)
+}
+
+/// Print length and complexity of every code and the sum of the complexities.
+pub fn complexity(args: &Args, layout: &ChainLayout, input: &Input) -> Result<()> {
+    let mut solver = Solver::new(layout, args.depth);
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut total: u128 = 0;
+    writeln!(out, "{:<8}{:>24}{:>24}", "code", "length", "complexity")?;
+    for code in codes(args, input)? {
+        let length = solver.length(code)?;
+        let complexity = solver.complexity(code)?;
+        total = total
+            .checked_add(complexity)
+            .ok_or_else(|| eyre!("sum of the complexities does not fit in u128"))?;
+        writeln!(out, "{code:<8}{length:>24}{complexity:>24}")?;
+    }
+    writeln!(
+        out,
+        "{:<32}{total:>24}",
+        format!("sum at depth {}", args.depth)
+    )?;
+    Ok(out.flush()?)
+}
+
+/// Print the events of the selected sequence as JSON Lines.
+pub fn trace(args: &Args, layout: &ChainLayout, input: &Input) -> Result<()> {
+    let sequences = input.solved(layout, args.depth);
+    let line = args.line.unwrap_or(0);
+    let sequence = selected(args, &sequences, line)?
+        .as_ref()
+        .map_err(|e| eyre!("cannot select sequence {}, {e}", line + 1))?;
+    let events = Simulator::with_layout(layout, args.depth).trace(&sequence.presses);
+    let mut out = BufWriter::new(io::stdout().lock());
+    write_jsonl(&events, &mut out).wrap_err("cannot write trace")?;
+    Ok(out.flush()?)
+}
+
+/// Sequence `line` of `sequences`, counting from 0.
+fn selected<'a, T>(args: &Args, sequences: &'a [T], line: usize) -> Result<&'a T> {
+    sequences.get(line).ok_or_else(|| {
+        eyre!(
+            "{} has {} sequences, cannot select sequence {}",
+            args.input.display(),
+            sequences.len(),
+            line + 1
+        )
+    })
+}
+
+/// Where sequence `i` of the input was read from, for messages.
+fn location(args: &Args, line: Option<usize>, i: usize) -> String {
+    match line {
+        Some(line) => format!("{}:{line}", args.input.display()),
+        None => format!("sequence {}", i + 1),
+    }
+}
+
+/// Run every sequence through the chain, or only the one selected with
+/// `--line`, and print output, the presses on every pad, where the arms end
+/// up and the fault if a robot panics. Codes whose sequence cannot be built
+/// are skipped with a note on standard error. In JSON, `line` is the line
+/// of the input file the sequence or code was read from. Returns false if
+/// any robot panicked or any sequence was skipped.
+pub fn simulate(args: &Args, layout: &ChainLayout, input: &Input) -> Result<bool> {
+    let mut sim = Simulator::with_layout(layout, args.depth);
+    let pad_count = sim.pad_count();
+    let show = |s: &str| -> String {
+        if args.arrows {
+            s.chars().map(display_label).collect()
+        } else {
+            s.to_string()
+        }
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut ok = true;
+    let sequences = input.solved(layout, args.depth);
+    let range = match args.line {
+        Some(line) => selected(args, &sequences, line).map(|_| line..line + 1)?,
+        None => 0..sequences.len(),
+    };
+    for (i, sequence) in sequences
+        .iter()
+        .enumerate()
+        .take(range.end)
+        .skip(range.start)
+    {
+        let sequence = match sequence {
+            Ok(sequence) => sequence,
+            Err(e) => {
+                let line = input
+                    .codes
+                    .get(i - input.lines.len())
+                    .map(|(line, _)| *line);
+                eprintln!("{}: skipped, {e}", location(args, line, i));
+                ok = false;
+                continue;
+            }
+        };
+        let events = sim.trace(&sequence.presses);
+        let presses = presses_by_pad(&events, pad_count);
+        let state = sim.state();
+        ok &= state.fault.is_none();
+        let labels: Vec<char> = (0..pad_count)
+            .map(|pad| {
+                sim.keypad(pad)
+                    .label(state.pads[pad].pos)
+                    .expect("arm rests on a button")
+            })
+            .collect();
+
+        if args.json {
+            let presses: Vec<String> = presses.iter().map(|p| json_string(p)).collect();
+            let positions: Vec<String> = state
+                .pads
+                .iter()
+                .zip(&labels)
+                .map(|(p, label)| {
+                    format!(
+                        r#"{{"label":{},"pos":[{},{}]}}"#,
+                        json_string(&label.to_string()),
+                        p.pos.0,
+                        p.pos.1
+                    )
+                })
+                .collect();
+            let fault = events
+                .iter()
+                .find(|e| matches!(e, Event::Fault { .. }))
+                .map_or_else(|| "null".to_string(), Event::to_json);
+            writeln!(
+                out,
+                r#"{{"line":{},"code":{},"sequence":{},"output":{},"presses":[{}],"positions":[{}],"fault":{fault}}}"#,
+                sequence.line.map_or("null".to_string(), |l| l.to_string()),
+                sequence
+                    .code
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+                json_string(&sequence.presses),
+                json_string(&state.output),
+                presses.join(","),
+                positions.join(",")
+            )?;
+            continue;
+        }
+
+        let title = match &sequence.code {
+            Some(code) => format!("{code}, {} presses", sequence.presses.len()),
+            None => format!("{} presses", sequence.presses.len()),
+        };
+        writeln!(out, "{}: {title}", location(args, sequence.line, i))?;
+        writeln!(out, "  {:<14}{}", "output", state.output)?;
+        for (pad, p) in presses.iter().enumerate() {
+            let p = if pad < pad_count - 1 {
+                show(p)
+            } else {
+                p.clone()
+            };
+            writeln!(out, "  {:<14}{p}", pad_name(pad, pad_count))?;
+        }
+        let positions: Vec<String> = labels
+            .iter()
+            .enumerate()
+            .map(|(pad, label)| {
+                let label = if pad < pad_count - 1 {
+                    show(&label.to_string())
+                } else {
+                    label.to_string()
+                };
+                let (x, y) = state.pads[pad].pos;
+                format!("{label} ({x}, {y})")
+            })
+            .collect();
+        writeln!(out, "  {:<14}{}", "arms", positions.join("  "))?;
+        if let Some(fault) = &state.fault {
+            for (j, line) in fault_report(fault, pad_count).iter().enumerate() {
+                let head = if j == 0 { "fault" } else { "" };
+                writeln!(out, "  {head:<14}{line}")?;
+            }
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(ok)
+}
+
+/// Run every sequence that has an expected result and print whether it gives
+/// it. Rejected lines with `=>` fail too, as their assertion is never
+/// checked. Returns false if any sequence fails.
+pub fn check(args: &Args, layout: &ChainLayout, input: &Input) -> Result<bool> {
+    let mut sim = Simulator::with_layout(layout, args.depth);
+    let mut out = BufWriter::new(io::stdout().lock());
+    let (mut passed, mut failed, mut unchecked) = (0, 0, 0);
+    for (i, sequence) in input.sequences(layout, args.depth).iter().enumerate() {
+        let Some(expect) = &sequence.expect else {
+            unchecked += 1;
+            continue;
+        };
+        sim.reset();
+        sim.run(&sequence.presses);
+        let failures = expect.failures(&sim);
+        let mut preview: String = sequence.presses.chars().take(40).collect();
+        if preview.len() < sequence.presses.len() {
+            preview += "...";
+        }
+        let line = location(args, sequence.line, i);
+        if failures.is_empty() {
+            passed += 1;
+            writeln!(out, "ok    {line}  {preview}")?;
+        } else {
+            failed += 1;
+            writeln!(out, "FAIL  {line}  {preview}: {}", failures.join(", "))?;
+        }
+    }
+    for rejected in input.rejected.iter().filter(|r| r.text.contains("=>")) {
+        failed += 1;
+        writeln!(
+            out,
+            "FAIL  {}:{}  {}: {}",
+            args.input.display(),
+            rejected.line,
+            rejected.text,
+            rejected.reason
+        )?;
+    }
+    writeln!(
+        out,
+        "{passed} passed, {failed} failed, {unchecked} without assertion"
+    )?;
+    out.flush()?;
+    Ok(failed == 0)
+}
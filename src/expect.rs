@@ -0,0 +1,143 @@
+//! Expected results written after `=>` on a sequence line.
+//!
+//! `DLLARRUA => 0` expects the num pad to type `0` and no robot to panic.
+//! The output may be left out, then nothing must be typed. After it may
+//! follow `arms` with the button every arm rests on at the end, manual pad
+//! first, and `fault` if a robot has to panic:
+//!
+//! ```text
+//! DLLARRUA => arms AAU3
+//! <A^A => fault
+//! ```
+
+use crate::keypad::{from_arrow, Keypad};
+use crate::sim::Simulator;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expect {
+    /// What the num pad types, up to the fault if there is one.
+    pub output: String,
+    /// Button under every arm at the end, in `U D L R A` form.
+    pub arms: Option<String>,
+    /// Whether a robot has to panic.
+    pub fault: bool,
+}
+
+impl Expect {
+    /// Read the text after `=>`, outputs being made of buttons of `numeric`.
+    pub fn parse(text: &str, numeric: &Keypad) -> Result<Self, String> {
+        let mut expect = Expect {
+            output: String::new(),
+            arms: None,
+            fault: false,
+        };
+        let mut words = text.split_whitespace().peekable();
+        if let Some(output) = words.next_if(|w| w.chars().all(|c| numeric.position(c).is_some())) {
+            expect.output = output.to_string();
+        }
+        while let Some(word) = words.next() {
+            match word {
+                "fault" => expect.fault = true,
+                "arms" => {
+                    let arms = words.next().ok_or("arms needs a button for every pad")?;
+                    expect.arms = Some(arms.chars().map(from_arrow).collect());
+                }
+                w => return Err(format!("unexpected {w:?} after =>")),
+            }
+        }
+        Ok(expect)
+    }
+
+    /// How the chain of `sim` differs from what is expected, empty if it
+    /// matches.
+    pub fn failures(&self, sim: &Simulator) -> Vec<String> {
+        let state = sim.state();
+        let mut failures = vec![];
+        if state.output != self.output {
+            failures.push(format!(
+                "output {:?}, expected {:?}",
+                state.output, self.output
+            ));
+        }
+        match (&state.fault, self.fault) {
+            (None, true) => failures.push("no robot panics, expected a fault".to_string()),
+            (Some(fault), false) => {
+                failures.push(format!("a robot panics at step {}", fault.step + 1))
+            }
+            _ => {}
+        }
+        if let Some(arms) = &self.arms {
+            let actual: String = (0..sim.pad_count())
+                .map(|pad| {
+                    sim.keypad(pad)
+                        .label(state.pads[pad].pos)
+                        .expect("arm rests on a button")
+                })
+                .collect();
+            if actual != *arms {
+                failures.push(format!("arms on {actual}, expected {arms}"));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Expect, String> {
+        Expect::parse(text, &Keypad::numeric())
+    }
+
+    /// Failures of `expect` after running `presses` through a chain with
+    /// `depth` robot pads.
+    fn failures(expect: &str, depth: usize, presses: &str) -> Vec<String> {
+        let mut sim = Simulator::new(depth);
+        sim.run(presses);
+        parse(expect).unwrap().failures(&sim)
+    }
+
+    #[test]
+    fn parse_output_arms_and_fault() {
+        let expect = parse(" 029A ").unwrap();
+        assert_eq!(expect.output, "029A");
+        assert_eq!((expect.arms, expect.fault), (None, false));
+
+        let expect = parse("0 arms AA^3").unwrap();
+        assert_eq!(expect.output, "0");
+        assert_eq!(expect.arms.as_deref(), Some("AAU3"));
+
+        let expect = parse("fault").unwrap();
+        assert_eq!((expect.output.as_str(), expect.fault), ("", true));
+
+        let expect = parse("").unwrap();
+        assert_eq!(expect.output, "");
+        assert_eq!((expect.arms, expect.fault), (None, false));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_words() {
+        assert_eq!(
+            parse("0 bogus").unwrap_err(),
+            r#"unexpected "bogus" after =>"#
+        );
+        assert!(parse("0 arms").is_err());
+        assert!(parse("fault 0").is_err());
+    }
+
+    #[test]
+    fn failures_compare_output_arms_and_fault() {
+        assert!(failures("0", 0, "LA").is_empty());
+        assert!(failures("0 arms A0", 0, "LA").is_empty());
+        assert_eq!(failures("1", 0, "LA"), [r#"output "0", expected "1""#]);
+        assert_eq!(failures("0 arms AA", 0, "LA"), ["arms on A0, expected AA"]);
+        assert_eq!(
+            failures("0 fault", 0, "LA"),
+            ["no robot panics, expected a fault"]
+        );
+        assert!(failures("fault", 0, "LLA").is_empty());
+        assert_eq!(failures("", 0, "LLA"), ["a robot panics at step 2"]);
+        assert_eq!(failures("0", 0, "LLA").len(), 2);
+    }
+}
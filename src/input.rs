@@ -0,0 +1,379 @@
+//! Reading sequences and numeric codes from an input file.
+//!
+//! Sequence lines use `U D L R A` or `^ v < > A`, but not both in one line.
+//! Lines made of buttons of the numeric pad, like `029A`, are codes, which
+//! get a shortest sequence generated by [`Input::sequences`]. A sequence
+//! may be followed by `=>` and the result it should have, see
+//! [`crate::expect`]. Empty lines and comments starting with `#` are
+//! skipped, every other line is rejected and listed in [`Input::rejected`].
+
+use color_eyre::eyre::{bail, Result, WrapErr};
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::expect::Expect;
+use crate::keypad::{display_label, from_arrow, ChainLayout, Keypad};
+use crate::solver::{Solver, MAX_SEQUENCE_LEN};
+
+/// Input file used when no path is given.
+pub const DEFAULT_INPUT: &str = "keypad2024input.txt";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Input {
+    /// Sequences typed in the input file.
+    pub lines: Vec<Sequence>,
+    /// Numeric codes, each with the line it was read from, counting from 1.
+    pub codes: Vec<(usize, String)>,
+    pub rejected: Vec<Rejected>,
+}
+
+impl Input {
+    /// Number of sequences [`Input::sequences`] returns.
+    pub fn sequence_count(&self) -> usize {
+        self.lines.len() + self.codes.len()
+    }
+
+    /// The typed sequences, followed by a shortest sequence for every code in
+    /// a chain with `depth` robot pads, without presses for codes whose
+    /// sequence cannot be built.
+    pub fn sequences(&self, layout: &ChainLayout, depth: usize) -> Vec<Sequence> {
+        let mut solver = Solver::new(layout, depth);
+        let typed = self.lines.iter().cloned();
+        let generated = self
+            .codes
+            .iter()
+            .map(|(line, c)| Sequence::for_code_or_empty(&mut solver, c, Some(*line)));
+        typed.chain(generated).collect()
+    }
+
+    /// Like [`Input::sequences`], with the error for every code whose
+    /// sequence cannot be built.
+    pub fn solved(&self, layout: &ChainLayout, depth: usize) -> Vec<Result<Sequence>> {
+        let mut solver = Solver::new(layout, depth);
+        let typed = self.lines.iter().cloned().map(Ok);
+        let generated = self
+            .codes
+            .iter()
+            .map(|(line, c)| Sequence::for_code(&mut solver, c, Some(*line)));
+        typed.chain(generated).collect()
+    }
+}
+
+/// Manual presses to simulate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sequence {
+    /// Presses in `U D L R A` form.
+    pub presses: String,
+    /// The code the presses were generated for, if any.
+    pub code: Option<String>,
+    /// Result written after `=>`.
+    pub expect: Option<Expect>,
+    /// Line of the input file the presses or the code were read from,
+    /// counting from 1.
+    pub line: Option<usize>,
+}
+
+impl Sequence {
+    /// A shortest sequence typing `code`, read from `line` of the input
+    /// file if it was.
+    pub fn for_code(solver: &mut Solver, code: &str, line: Option<usize>) -> Result<Self> {
+        Ok(Self {
+            presses: solver.solve(code)?,
+            code: Some(code.to_string()),
+            expect: None,
+            line,
+        })
+    }
+
+    /// A shortest sequence typing `code`, without presses if it is too long
+    /// to build.
+    pub fn for_code_or_empty(solver: &mut Solver, code: &str, line: Option<usize>) -> Self {
+        Self::for_code(solver, code, line).unwrap_or_else(|_| Self {
+            presses: String::new(),
+            code: Some(code.to_string()),
+            expect: None,
+            line,
+        })
+    }
+}
+
+/// A line of the input file that is neither a sequence nor a code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejected {
+    /// Line number, counting from 1.
+    pub line: usize,
+    pub text: String,
+    pub reason: Reason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// `char` is no button of any pad, `column` counts from 1.
+    BadChar { column: usize, char: char },
+    /// Buttons of different pads, or letters and arrows, in one line.
+    MixedAlphabets,
+    /// The sequence has more presses than [`MAX_SEQUENCE_LEN`].
+    OverLimit(usize),
+    /// The text after `=>` cannot be read.
+    BadAssertion(String),
+    /// Nothing but an assertion, no presses before `=>`.
+    NoPresses,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::BadChar { column, char } => {
+                write!(f, "bad character {char:?} at column {column}")
+            }
+            Reason::MixedAlphabets => write!(f, "mixed alphabets"),
+            Reason::OverLimit(len) => {
+                write!(f, "over limit, {len} presses of at most {MAX_SEQUENCE_LEN}")
+            }
+            Reason::BadAssertion(e) => write!(f, "bad assertion, {e}"),
+            Reason::NoPresses => write!(f, "no presses before =>"),
+        }
+    }
+}
+
+/// Read `path`, or standard input if `path` is `-`.
+pub fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .wrap_err("cannot read standard input")?;
+        return Ok(text);
+    }
+    read_to_string(path).wrap_err_with(|| format!("cannot read input file {}", path.display()))
+}
+
+/// Read and parse `path`, see [`read_input`].
+pub fn load(path: &Path, numeric: &Keypad) -> Result<Input> {
+    Ok(parse(&read_input(path)?, numeric))
+}
+
+/// Put `presses` in place of the presses on line `line` of `path`, counting
+/// from 1, keeping what follows `=>` and the alphabet the line was written
+/// in. The rest of the file, line endings included, stays as it is. Fails
+/// if the line no longer holds `old`.
+pub fn replace_sequence(path: &Path, line: usize, old: &str, presses: &str) -> Result<()> {
+    let mut text = read_saved(path)?;
+    let Some(range) = line_range(&text, line) else {
+        bail!("{} has no line {line} any more", path.display());
+    };
+    let current = &text[range.clone()];
+    let (sequence, expect) = match current.split_once("=>") {
+        Some((sequence, expect)) => (sequence.trim(), Some(expect.trim())),
+        None => (current.trim(), None),
+    };
+    if sequence.chars().map(from_arrow).collect::<String>() != old {
+        bail!(
+            "line {line} of {} changed since it was read",
+            path.display()
+        );
+    }
+    let mut new = if sequence.chars().any(|c| "<>^v".contains(c)) {
+        presses.chars().map(display_label).collect()
+    } else {
+        presses.to_string()
+    };
+    if let Some(expect) = expect {
+        new = format!("{new} => {expect}");
+    }
+    text.replace_range(range, &new);
+    write(path, text).wrap_err_with(|| format!("cannot write input file {}", path.display()))
+}
+
+/// Byte range of line `line` of `text`, counting from 1, without its line
+/// ending.
+fn line_range(text: &str, line: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+    for (n, l) in text.split_inclusive('\n').enumerate() {
+        if n + 1 == line {
+            let l = l.strip_suffix('\n').unwrap_or(l);
+            let l = l.strip_suffix('\r').unwrap_or(l);
+            return Some(start..start + l.len());
+        }
+        start += l.len();
+    }
+    None
+}
+
+/// Add `presses` as a new line at the end of `path`, returning its line
+/// number, counting from 1. The line ends like the lines of the file.
+pub fn append_sequence(path: &Path, presses: &str) -> Result<usize> {
+    let mut text = read_saved(path)?;
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push_str(newline);
+    }
+    text.push_str(presses);
+    text.push_str(newline);
+    write(path, &text).wrap_err_with(|| format!("cannot write input file {}", path.display()))?;
+    Ok(text.lines().count())
+}
+
+/// Text of an input file that changes are written back to.
+fn read_saved(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        bail!("the input was read from standard input, there is no file to save to");
+    }
+    read_input(path)
+}
+
+/// Split `text` into sequences and codes, codes being lines made of buttons
+/// of the `numeric` pad.
+pub fn parse(text: &str, numeric: &Keypad) -> Input {
+    let mut input = Input::default();
+    for (n, line) in text.lines().enumerate() {
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let (l, expect) = match l.split_once("=>") {
+            Some((l, expect)) => (l.trim_end(), Some(Expect::parse(expect, numeric))),
+            None => (l, None),
+        };
+        let reason = if l.is_empty() {
+            Reason::NoPresses
+        } else if l.chars().all(|c| "<>^vA".contains(c)) || l.chars().all(|c| "LRUDA".contains(c)) {
+            match expect.transpose() {
+                _ if l.len() as u128 > MAX_SEQUENCE_LEN => Reason::OverLimit(l.len()),
+                Err(e) => Reason::BadAssertion(e),
+                Ok(expect) => {
+                    input.lines.push(Sequence {
+                        presses: l.chars().map(from_arrow).collect(),
+                        code: None,
+                        expect,
+                        line: Some(n + 1),
+                    });
+                    continue;
+                }
+            }
+        } else if l.chars().all(|c| numeric.position(c).is_some()) {
+            if expect.is_some() {
+                Reason::BadAssertion("codes take no assertion".to_string())
+            } else {
+                input.codes.push((n + 1, l.to_string()));
+                continue;
+            }
+        } else {
+            let known = |c: char| "<>^vLRUDA".contains(c) || numeric.position(c).is_some();
+            match l.chars().position(|c| !known(c)) {
+                Some(i) => Reason::BadChar {
+                    column: i + 1,
+                    char: l.chars().nth(i).expect("position is in the line"),
+                },
+                None => Reason::MixedAlphabets,
+            }
+        };
+        input.rejected.push(Rejected {
+            line: n + 1,
+            text: line.trim().to_string(),
+            reason,
+        });
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::process;
+
+    fn parse_text(text: &str) -> Input {
+        parse(text, &Keypad::numeric())
+    }
+
+    /// Why the only line of `text` is rejected.
+    fn reason(text: &str) -> Reason {
+        let input = parse_text(text);
+        assert!(input.lines.is_empty() && input.codes.is_empty(), "{text}");
+        assert_eq!(input.rejected.len(), 1, "{text}");
+        input.rejected[0].reason.clone()
+    }
+
+    #[test]
+    fn parse_sequences_and_codes() {
+        let input = parse_text("# comment\n\n  <A => 0\nDLLA\n029A\nA\n");
+        let presses: Vec<&str> = input.lines.iter().map(|s| s.presses.as_str()).collect();
+        assert_eq!(presses, ["LA", "DLLA", "A"]);
+        let lines: Vec<_> = input.lines.iter().map(|s| s.line).collect();
+        assert_eq!(lines, [Some(3), Some(4), Some(6)]);
+        assert_eq!(input.lines[0].expect.as_ref().unwrap().output, "0");
+        assert_eq!(input.lines[1].expect, None);
+        assert_eq!(input.codes, [(5, "029A".to_string())]);
+        assert!(input.rejected.is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_lines() {
+        assert_eq!(
+            reason("DLXA"),
+            Reason::BadChar {
+                column: 3,
+                char: 'X'
+            }
+        );
+        assert_eq!(
+            reason("  <<x => 0"),
+            Reason::BadChar {
+                column: 3,
+                char: 'x'
+            }
+        );
+        assert_eq!(reason("<LA"), Reason::MixedAlphabets);
+        assert_eq!(reason("DL0A"), Reason::MixedAlphabets);
+        assert_eq!(reason("=> 0"), Reason::NoPresses);
+        assert_eq!(reason("  => 0"), Reason::NoPresses);
+        let long = "A".repeat(MAX_SEQUENCE_LEN as usize + 1);
+        assert_eq!(reason(&long), Reason::OverLimit(long.len()));
+        assert!(matches!(reason("DLA => 0 bogus"), Reason::BadAssertion(_)));
+        assert!(matches!(reason("029A => 0"), Reason::BadAssertion(_)));
+
+        let input = parse_text("LA\nDXA\n\nLA\n");
+        assert_eq!(input.lines.len(), 2);
+        assert_eq!(input.rejected[0].line, 2);
+        assert_eq!(input.rejected[0].text, "DXA");
+    }
+
+    /// Run `f` on a file holding `text` and return what the file holds then.
+    fn edit_file<F: FnOnce(&Path)>(name: &str, text: &str, f: F) -> String {
+        let path = temp_dir().join(format!("keysim-{name}-{}.txt", process::id()));
+        write(&path, text).unwrap();
+        f(&path);
+        let text = read_to_string(&path).unwrap();
+        remove_file(&path).unwrap();
+        text
+    }
+
+    #[test]
+    fn replace_keeps_line_endings() {
+        let text = "# crlf\r\n<A => 0\r\nDLLA\r\n029A";
+        let text = edit_file("replace", text, |path| {
+            replace_sequence(path, 2, "LA", "LLA").unwrap();
+            replace_sequence(path, 3, "DLLA", "DA").unwrap();
+            assert!(replace_sequence(path, 3, "DLLA", "A").is_err());
+            assert!(replace_sequence(path, 5, "", "A").is_err());
+        });
+        assert_eq!(text, "# crlf\r\n<<A => 0\r\nDA\r\n029A");
+    }
+
+    #[test]
+    fn append_ends_the_line_like_the_file() {
+        let text = edit_file("append", "DLLA\r\n029A", |path| {
+            assert_eq!(append_sequence(path, "LA").unwrap(), 3);
+        });
+        assert_eq!(text, "DLLA\r\n029A\r\nLA\r\n");
+        let text = edit_file("append-lf", "", |path| {
+            assert_eq!(append_sequence(path, "LA").unwrap(), 1);
+        });
+        assert_eq!(text, "LA\n");
+    }
+}
@@ -0,0 +1,273 @@
+//! Keypad geometry shared by simulation and rendering.
+//!
+//! A layout file describes the directional pad and the numeric pad as grids
+//! of single character labels, with `.` marking a gap:
+//!
+//! ```text
+//! # comment
+//! [directional]
+//! .^A
+//! <v>
+//!
+//! [numeric]
+//! 789
+//! 456
+//! 123
+//! .0A
+//! ```
+//!
+//! The directional pad may use `^ v < >` or `U D L R` and needs exactly one
+//! each of `U D L R A`. The numeric pad needs an `A`. Every arm starts on `A`.
+//! A section that is left out keeps the Advent of Code layout.
+
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::sim::Pos;
+
+/// Labels of the directional pad, in the internal `U D L R A` form.
+pub const DIR_LABELS: [char; 5] = ['U', 'D', 'L', 'R', 'A'];
+
+/// Grid of labelled buttons, with gaps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keypad {
+    width: i32,
+    height: i32,
+    cells: Vec<Option<char>>,
+    start: Pos,
+}
+
+impl Keypad {
+    /// The directional pad of the puzzle.
+    pub fn directional() -> Self {
+        Self::from_rows(&[".UA", "LDR"]).expect("built in directional pad")
+    }
+
+    /// The numeric pad of the puzzle.
+    pub fn numeric() -> Self {
+        Self::from_rows(&["789", "456", "123", ".0A"]).expect("built in numeric pad")
+    }
+
+    /// Build a pad from grid rows, `.` is a gap. Short rows are filled up with
+    /// gaps. The arm starts on `A`.
+    pub fn from_rows(rows: &[&str]) -> Result<Self> {
+        let height = rows.len();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        if width == 0 {
+            bail!("keypad has no buttons");
+        }
+        let mut cells = vec![None; width * height];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '.' {
+                    continue;
+                }
+                if cells.contains(&Some(c)) {
+                    bail!("button {c} appears twice");
+                }
+                cells[y * width + x] = Some(c);
+            }
+        }
+        let index = cells
+            .iter()
+            .position(|c| *c == Some('A'))
+            .ok_or_else(|| eyre!("keypad has no A button to start on"))?;
+        let pad = Self {
+            width: width as i32,
+            height: height as i32,
+            start: ((index % width) as i32, (index / width) as i32),
+            cells,
+        };
+        let mut reached = vec![pad.start];
+        let mut todo = vec![pad.start];
+        while let Some(pos) = todo.pop() {
+            for dir in ['U', 'D', 'L', 'R'] {
+                let next = moved(pos, dir).expect("dir is a direction");
+                if pad.label(next).is_some() && !reached.contains(&next) {
+                    reached.push(next);
+                    todo.push(next);
+                }
+            }
+        }
+        if reached.len() != pad.buttons().count() {
+            bail!("some buttons cannot be reached from A");
+        }
+        Ok(pad)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Position of the `A` button, where the arm starts.
+    pub fn start(&self) -> Pos {
+        self.start
+    }
+
+    pub fn on_grid(&self, pos: Pos) -> bool {
+        (0..self.width).contains(&pos.0) && (0..self.height).contains(&pos.1)
+    }
+
+    /// Label of the button at `pos`, `None` for gaps and positions off the grid.
+    pub fn label(&self, pos: Pos) -> Option<char> {
+        if !self.on_grid(pos) {
+            return None;
+        }
+        self.cells[(pos.1 * self.width + pos.0) as usize]
+    }
+
+    /// Position of the button labelled `label`.
+    pub fn position(&self, label: char) -> Option<Pos> {
+        self.buttons()
+            .find(|(_, c)| *c == label)
+            .map(|(pos, _)| pos)
+    }
+
+    /// All buttons with their positions, row by row.
+    pub fn buttons(&self) -> impl Iterator<Item = (Pos, char)> + '_ {
+        self.cells.iter().enumerate().filter_map(|(i, c)| {
+            let pos = (i as i32 % self.width, i as i32 / self.width);
+            c.map(|c| (pos, c))
+        })
+    }
+
+    /// True if the pad has exactly the buttons `U D L R A`.
+    pub fn is_directional(&self) -> bool {
+        let mut labels: Vec<char> = self.buttons().map(|(_, c)| c).collect();
+        labels.sort_unstable();
+        let mut expected = DIR_LABELS;
+        expected.sort_unstable();
+        labels == expected
+    }
+}
+
+/// Position one button away from `pos` in direction `dir`, one of `U D L R`.
+pub fn moved(pos: Pos, dir: char) -> Option<Pos> {
+    let (x, y) = pos;
+    match dir {
+        'U' => Some((x, y - 1)),
+        'D' => Some((x, y + 1)),
+        'L' => Some((x - 1, y)),
+        'R' => Some((x + 1, y)),
+        _ => None,
+    }
+}
+
+/// Label as shown on screen, directions are drawn as arrows.
+pub fn display_label(label: char) -> char {
+    match label {
+        'U' => '^',
+        'D' => 'v',
+        'L' => '<',
+        'R' => '>',
+        c => c,
+    }
+}
+
+/// Map arrows to the internal `U D L R` form, other characters pass through.
+pub fn from_arrow(c: char) -> char {
+    match c {
+        '^' => 'U',
+        'v' => 'D',
+        '<' => 'L',
+        '>' => 'R',
+        c => c,
+    }
+}
+
+/// The two pad designs a chain is built from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainLayout {
+    pub directional: Keypad,
+    pub numeric: Keypad,
+}
+
+impl Default for ChainLayout {
+    fn default() -> Self {
+        Self {
+            directional: Keypad::directional(),
+            numeric: Keypad::numeric(),
+        }
+    }
+}
+
+impl ChainLayout {
+    /// Pads of a chain with `depth` directional robot pads: the manual pad,
+    /// the robot pads and the numeric pad, in the order signals travel.
+    pub fn pads(&self, depth: usize) -> Vec<Keypad> {
+        let mut pads = vec![self.directional.clone(); depth + 1];
+        pads.push(self.numeric.clone());
+        pads
+    }
+
+    /// Width of the wider of the two pads, in buttons.
+    pub fn max_width(&self) -> i32 {
+        self.directional.width().max(self.numeric.width())
+    }
+
+    /// Height of the taller of the two pads, in buttons.
+    pub fn max_height(&self) -> i32 {
+        self.directional.height().max(self.numeric.height())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = read_to_string(path)
+            .wrap_err_with(|| format!("cannot read layout file {}", path.display()))?;
+        Self::parse(&text).wrap_err_with(|| format!("in layout file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut layout = Self::default();
+        let mut section: Option<(usize, &str)> = None;
+        let mut rows: Vec<String> = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if let Some(s) = section {
+                    layout.set_section(s, &rows)?;
+                }
+                section = Some((i + 1, name.trim()));
+                rows.clear();
+                continue;
+            }
+            if section.is_none() {
+                bail!("line {}: grid row before the first [section]", i + 1);
+            }
+            rows.push(line.to_string());
+        }
+        if let Some(s) = section {
+            layout.set_section(s, &rows)?;
+        }
+        Ok(layout)
+    }
+
+    fn set_section(&mut self, (line, name): (usize, &str), rows: &[String]) -> Result<()> {
+        let rows: Vec<String> = match name {
+            "directional" => rows
+                .iter()
+                .map(|r| r.chars().map(from_arrow).collect())
+                .collect(),
+            "numeric" => rows.to_vec(),
+            _ => bail!("line {line}: unknown section [{name}]"),
+        };
+        let rows: Vec<&str> = rows.iter().map(|r| r.as_str()).collect();
+        let pad = Keypad::from_rows(&rows).wrap_err_with(|| format!("line {line}"))?;
+        if name == "directional" {
+            if !pad.is_directional() {
+                bail!("line {line}: directional pad needs exactly the buttons ^ v < > A");
+            }
+            self.directional = pad;
+        } else {
+            self.numeric = pad;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+//! Simulation engine behind the keysim TUI.
+//!
+//! The modules in here do not depend on a terminal, so scripts and tests can
+//! drive the keypad chain directly.
+
+pub mod expect;
+pub mod input;
+pub mod keypad;
+pub mod scores;
+pub mod sim;
+pub mod solver;
+pub mod timeline;
+pub mod trace;
+pub mod tree;
@@ -1,96 +1,377 @@
-//! # [Ratatui] Block example
+//! keysim, an interactive simulator for the keypad chain of Advent of Code
+//! 2024 day 21, with subcommands that print solutions and traces instead.
 //!
-//! The latest version of this example is available in the [examples] folder in the repository.
-//!
-//! Please note that the examples are designed to be run against the `main` branch of the Github
-//! repository. This means that you may not be able to compile with the latest release version on
-//! crates.io, or the one that you have installed locally.
-//!
-//! See the [examples readme] for more information on finding examples that match the version of the
-//! library you are using.
-//!
-//! [Ratatui]: https://github.com/ratatui/ratatui
-//! [examples]: https://github.com/ratatui/ratatui/blob/main/examples
-//! [examples readme]: https://github.com/ratatui/ratatui/blob/main/examples/README.md
+//! Run `keysim --help` for the command line.
+
+mod cli;
+mod commands;
 
-use color_eyre::{owo_colors::OwoColorize, Result};
+use color_eyre::Result;
 
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
-    layout::{Alignment, Constraint, Layout, Margin, Rect},
-    style::{Style, Stylize},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
     DefaultTerminal, Frame,
 };
-use std::collections::HashMap;
-use std::fs::read_to_string;
+use std::fs::File;
+use std::io::BufWriter;
+
+use cli::{Args, Command, HELP};
+use color_eyre::eyre::{bail, Report};
+use keysim::input::{self, Input, Rejected, Sequence};
+use keysim::keypad::{display_label, from_arrow, ChainLayout, DIR_LABELS};
+use keysim::scores::{self, Score, SCORE_FILE};
+use keysim::sim::{Fault, FaultKind, Simulator};
+use keysim::solver::{numeric_part, Solver};
+use keysim::timeline::Timeline;
+use keysim::trace::{write_jsonl, Event as TraceEvent};
+use keysim::tree::Tree;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Deepest robot chain that can be selected.
+const MAX_DEPTH: usize = 30;
+
+/// Presses skipped by PageUp and PageDown at startup.
+const DEFAULT_STRIDE: usize = 10;
+
+/// Largest stride `>` can set.
+const MAX_STRIDE: usize = 1 << 20;
+
+/// Autoplay speeds in presses per second.
+const SPEEDS: [u32; 11] = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024];
+
+/// Autoplay speed at startup, as index into [`SPEEDS`].
+const DEFAULT_SPEED: usize = 3;
+
+/// Most pads below the manual pad that get a row under the nav bar.
+const MAX_LEVEL_ROWS: usize = 6;
+
+/// Presses the nav bar keeps visible on either side of the cursor.
+const NAV_MARGIN: usize = 8;
+
+/// Width of the pad names in front of the nav bar rows.
+const LEVEL_LABEL_WIDTH: u16 = 5;
+
+/// Expansion trees with more nodes open with only the top press unfolded.
+const TREE_UNFOLD_LIMIT: usize = 200;
+
+/// Narrowest main area the sequence list is shown next to.
+const MIN_MAIN_WIDTH: u16 = 60;
+
+/// Width of the output, steps and depth boxes.
+const INFO_WIDTH: u16 = 25;
+
+/// Narrowest nav bar the terminal must leave room for.
+const MIN_NAV_WIDTH: u16 = 30;
+
+/// Width of the sequence list on the left.
+const LIST_WIDTH: u16 = 30;
+
+/// Number of best scores listed when a game ends.
+const SCORES_SHOWN: usize = 10;
+
+/// Shortest time between two frames during autoplay.
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// What the text typed into a [`Prompt`] is used for.
+enum PromptKind {
+    /// Numeric code to build a shortest sequence for.
+    Solve,
+    /// Numeric code to type in a game, a random one if empty.
+    Game,
+    /// Numeric code to show the next press towards.
+    Hint,
+    /// Numeric code to add the presses that type it for.
+    Autopilot,
+}
+
+/// Read only text shown in a popup until ESC is pressed.
+struct InfoPopup {
+    title: String,
+    lines: Vec<String>,
+}
+
+/// Expansion tree of one press, shown in a popup.
+struct TreeView {
+    tree: Tree,
+    /// Nodes whose children are hidden.
+    folded: HashSet<usize>,
+    /// Index into [`TreeView::rows`].
+    selected: usize,
+}
+
+impl TreeView {
+    /// Nodes that are not hidden by a folded parent, in display order, with
+    /// their depth in the tree.
+    fn rows(&self) -> Vec<(usize, usize)> {
+        let mut rows = vec![];
+        let mut stack = vec![(0, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            rows.push((id, depth));
+            if !self.folded.contains(&id) {
+                let children = &self.tree.nodes[id].children;
+                stack.extend(children.iter().rev().map(|c| (*c, depth + 1)));
+            }
+        }
+        rows
+    }
+}
+
+/// What keys do to the selected line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Keys move the cursor and select lines.
+    Browse,
+    /// Keys insert and delete presses at the cursor.
+    Edit,
+    /// Arrows and Enter press the manual pad, adding to the end of the line.
+    Record,
+}
+
+/// A change to a line made in edit mode.
+#[derive(Clone)]
+enum Edit {
+    Insert {
+        at: usize,
+        presses: String,
+    },
+    Delete {
+        at: usize,
+        presses: String,
+    },
+    /// `old` at `at` gives way to `new`.
+    Replace {
+        at: usize,
+        old: String,
+        new: String,
+    },
+}
+
+impl Edit {
+    /// The edit that takes this one back.
+    fn inverse(self) -> Self {
+        match self {
+            Edit::Insert { at, presses } => Edit::Delete { at, presses },
+            Edit::Delete { at, presses } => Edit::Insert { at, presses },
+            Edit::Replace { at, old, new } => Edit::Replace {
+                at,
+                old: new,
+                new: old,
+            },
+        }
+    }
+}
+
+/// Edit history of one line.
+struct Editor {
+    /// The line as it was read or last saved.
+    saved: Sequence,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+/// A code to type in record mode with as few presses as possible.
+struct Game {
+    target: String,
+    /// Length of a shortest sequence typing the target.
+    optimal: u128,
+    /// Whether a hint or the autopilot was used, which keeps the score out
+    /// of the score file.
+    assisted: bool,
+    /// Every manual press made so far, also those taken back later.
+    presses: usize,
+}
+
+/// Where the pads go on screen: the size of their boxes, whether output,
+/// steps and depth sit below them instead of next to them, and the pads on
+/// every row, which cover all pads unless they have to scroll.
+struct PadLayout {
+    compact: bool,
+    info_stacked: bool,
+    rows: Vec<Range<usize>>,
+    /// Box size of every pad, wide enough for its name.
+    sizes: Vec<(u16, u16)>,
+}
+
+/// Single line text input shown in a popup.
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+}
 
 struct App {
     should_exit: bool,
-    key_pressed: Option<KeyCode>,
-    pad_x: Vec<i32>,
-    pad_y: Vec<i32>,
-    pad_max_x: Vec<i32>,
-    pad_max_y: Vec<i32>,
-    pad_invalid: Vec<(i32, i32)>,
-    pad_has_error: Vec<bool>,
-    pad_just_moved_to: Vec<bool>,
-    pad_just_moved_from: Vec<Option<(i32, i32)>>,
-    pad_just_pressed: Vec<bool>,
-    input_pointer: i32,
-    input_lines: Vec<String>,
+    input_path: PathBuf,
+    layout: ChainLayout,
+    depth: usize,
+    timeline: Timeline,
+    input_pointer: usize,
+    input_lines: Vec<Sequence>,
     input_count: usize,
     akt_line: usize,
     show_help: bool,
-
-    input: String,
+    show_list: bool,
+    show_levels: bool,
+    info: Option<InfoPopup>,
+    codes: Vec<String>,
+    rejected: Vec<Rejected>,
+    line_fault: Option<Fault>,
+    /// Whether each line gives the result written after `=>`, `None` for
+    /// lines without one.
+    checks: Vec<Option<bool>>,
+    stride: usize,
+    jump_level: usize,
+    playing: bool,
+    speed: usize,
+    last_tick: Instant,
+    pad_scroll: usize,
+    line_scroll: usize,
+    /// First manual press shown in the nav bar.
+    nav_scroll: usize,
+    prompt: Option<Prompt>,
+    tree: Option<TreeView>,
+    mode: Mode,
+    /// Edit history of every line changed in edit or record mode, by line
+    /// index.
+    edits: HashMap<usize, Editor>,
+    game: Option<Game>,
+    /// Manual press that leads to the target fastest from the cursor.
+    hint: Option<char>,
+    message: String,
 }
 impl App {
-    fn new() -> Self {
-        let pad_x = vec![2, 2, 2, 2];
-        let pad_y = vec![0, 0, 0, 3];
-        let pad_max_x = vec![2, 2, 2, 2];
-        let pad_max_y = vec![1, 1, 1, 3];
-        let pad_invalid = vec![(0, 0), (0, 0), (0, 0), (0, 3)];
-        let pad_has_error = vec![false, false, false, false];
-        let pad_just_moved_to = vec![false, false, false, false];
-        let pad_just_moved_from: Vec<Option<(i32, i32)>> = vec![None, None, None, None];
-        let pad_just_pressed = vec![false, false, false, false];
-
+    fn new(layout: ChainLayout, input: Input, args: &Args) -> Self {
         Self {
             should_exit: false,
-            key_pressed: None,
-            pad_x,
-            pad_y,
-            pad_max_x,
-            pad_max_y,
-            pad_invalid,
-            pad_has_error,
-            pad_just_moved_to,
-            pad_just_moved_from,
-            pad_just_pressed,
-            input: String::new(),
-            input_pointer: 0,
-            input_lines: vec![],
-            input_count: 0,
-            akt_line: 0,
+            input_path: args.input.clone(),
+            // clamped by set_depth in run, which says so
+            depth: args.depth,
+            input_count: input.sequence_count(),
+            input_lines: input.sequences(&layout, args.depth.min(MAX_DEPTH)),
+            timeline: Timeline::new(
+                Simulator::with_layout(&layout, args.depth.min(MAX_DEPTH)),
+                "",
+            ),
+            layout,
+            input_pointer: args.pos,
+            akt_line: args.line.unwrap_or(0),
             show_help: false,
+            show_list: true,
+            show_levels: true,
+            info: None,
+            codes: input.codes.into_iter().map(|(_, code)| code).collect(),
+            rejected: input.rejected,
+            line_fault: None,
+            checks: vec![],
+            stride: DEFAULT_STRIDE,
+            jump_level: 1,
+            playing: false,
+            speed: DEFAULT_SPEED,
+            last_tick: Instant::now(),
+            pad_scroll: 0,
+            line_scroll: 0,
+            nav_scroll: 0,
+            prompt: None,
+            tree: None,
+            mode: Mode::Browse,
+            edits: HashMap::new(),
+            game: None,
+            hint: None,
+            message: String::new(),
         }
     }
     fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        self.input_lines = load_file();
-        self.input_count = self.input_lines.len();
+        self.set_depth(self.depth);
+        self.move_cursor(self.input_pointer);
+        if !self.rejected.is_empty() {
+            self.show_load_report();
+        }
         while !self.should_exit {
             terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events()?;
+            if !self.playing {
+                self.handle_events()?;
+                continue;
+            }
+            let frame_time =
+                Duration::from_secs_f64(1.0 / SPEEDS[self.speed] as f64).max(FRAME_TIME);
+            if event::poll(frame_time.saturating_sub(self.last_tick.elapsed()))? {
+                self.handle_events()?;
+            }
+            self.tick();
         }
         Ok(())
     }
+
+    /// Advance the cursor by the presses due since the last tick, stopping at
+    /// the end of the line and at a fault.
+    fn tick(&mut self) {
+        if !self.playing {
+            return;
+        }
+        let due = self.last_tick.elapsed().as_secs_f64() * SPEEDS[self.speed] as f64;
+        if due < 1.0 {
+            return;
+        }
+        self.last_tick = Instant::now();
+        let mut target = self.input_pointer.saturating_add(due as usize);
+        let mut reason = None;
+        if let Some(fault) = &self.line_fault {
+            if self.input_pointer <= fault.step && target > fault.step {
+                target = fault.step + 1;
+                reason = Some("stopped, a robot panicked");
+            }
+        }
+        if reason.is_none() && target >= self.timeline.len() {
+            reason = Some("stopped at the end of the string");
+        }
+        self.move_cursor(target);
+        if let Some(reason) = reason {
+            self.playing = false;
+            self.message = reason.to_string();
+        }
+    }
+
+    /// Start or pause autoplay.
+    fn toggle_play(&mut self) {
+        if !self.playing && self.input_pointer >= self.timeline.len() {
+            self.move_cursor(0);
+        }
+        self.playing = !self.playing;
+        self.last_tick = Instant::now();
+        self.message = if self.playing {
+            format!("playing at {} presses/s", SPEEDS[self.speed])
+        } else {
+            "paused".to_string()
+        };
+    }
+
+    fn change_speed(&mut self, faster: bool) {
+        self.speed = if faster {
+            (self.speed + 1).min(SPEEDS.len() - 1)
+        } else {
+            self.speed.saturating_sub(1)
+        };
+        self.message = format!("speed {} presses/s", SPEEDS[self.speed]);
+    }
     fn handle_events(&mut self) -> Result<()> {
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
-                if let KeyCode::Char(c) = key.code {
+                if self.prompt.is_some() {
+                    self.handle_prompt_key(key.code);
+                } else if self.tree.is_some() {
+                    self.handle_tree_key(key.code);
+                } else if self.mode == Mode::Edit {
+                    self.handle_edit_key(key);
+                } else if self.mode == Mode::Record {
+                    self.handle_record_key(key);
+                } else if let KeyCode::Char(c) = key.code {
                     match c {
                         'q' => {
                             self.should_exit = true;
@@ -98,16 +379,107 @@ impl App {
                         '?' => {
                             self.show_help = true;
                         }
+                        'd' => {
+                            self.set_depth(self.depth.saturating_sub(1));
+                        }
+                        'D' => {
+                            self.set_depth(self.depth + 1);
+                        }
+                        ',' => {
+                            self.pad_scroll = self.pad_scroll.saturating_sub(1);
+                        }
+                        '.' => {
+                            self.pad_scroll += 1;
+                        }
+                        'c' => {
+                            self.show_complexity();
+                        }
+                        'r' => {
+                            self.show_load_report();
+                        }
+                        't' => {
+                            self.export_trace();
+                        }
+                        'x' => {
+                            self.open_tree();
+                        }
+                        'i' => {
+                            self.start_editing(Mode::Edit);
+                        }
+                        'm' => {
+                            self.start_recording();
+                        }
+                        'l' => {
+                            self.show_list = !self.show_list;
+                        }
+                        'p' => {
+                            self.show_levels = !self.show_levels;
+                        }
+                        'f' => {
+                            self.jump_to_fault();
+                        }
+                        ' ' => {
+                            self.toggle_play();
+                        }
+                        '+' => {
+                            self.change_speed(true);
+                        }
+                        '-' => {
+                            self.change_speed(false);
+                        }
+                        '<' => {
+                            self.stride = (self.stride / 2).max(1);
+                            self.message = format!("stride {}", self.stride);
+                        }
+                        '>' => {
+                            self.stride = (self.stride * 2).min(MAX_STRIDE);
+                            self.message = format!("stride {}", self.stride);
+                        }
+                        'e' | 'E' => {
+                            let forward = c == 'e';
+                            self.jump_to(forward, "num pad output", |e| {
+                                e.iter().any(|e| matches!(e, TraceEvent::Emitted { .. }))
+                            });
+                        }
+                        'k' => {
+                            let pad_count = self.timeline.simulator().pad_count();
+                            self.jump_level = (self.jump_level + 1) % pad_count;
+                            self.message = format!(
+                                "[ and ] jump to presses on {}",
+                                pad_name(self.jump_level, pad_count)
+                            );
+                        }
+                        '[' | ']' => {
+                            let forward = c == ']';
+                            let level = self.jump_level;
+                            let name = pad_name(level, self.timeline.simulator().pad_count());
+                            self.jump_to(forward, &format!("press on {name}"), |e| {
+                                e.iter().any(
+                                    |e| matches!(e, TraceEvent::Pressed { pad, .. } if *pad == level),
+                                )
+                            });
+                        }
+                        's' => {
+                            self.prompt = Some(Prompt {
+                                kind: PromptKind::Solve,
+                                text: String::new(),
+                            });
+                        }
+                        'g' => {
+                            self.prompt = Some(Prompt {
+                                kind: PromptKind::Game,
+                                text: String::new(),
+                            });
+                        }
+                        'h' => {
+                            self.assist(PromptKind::Hint);
+                        }
+                        'a' => {
+                            self.assist(PromptKind::Autopilot);
+                        }
                         _ => {
-                            let c_i = c.to_digit(10);
-                            if c_i.is_some() && c_i.unwrap() > 0 {
-                                let n = c_i.unwrap() - 1;
-                                if n < self.input_count as u32 {
-                                    self.akt_line = (n) as usize;
-                                    self.input_pointer = self
-                                        .input_pointer
-                                        .min(self.input_lines[n as usize].len() as i32);
-                                }
+                            if let Some(n) = c.to_digit(10).filter(|n| *n > 0) {
+                                self.select_line(n as usize - 1);
                             }
                         }
                     }
@@ -115,10 +487,33 @@ impl App {
                     match key.code {
                         KeyCode::Esc => {
                             self.show_help = false;
+                            self.info = None;
                         }
-                        _ => {
-                            self.key_pressed = Some(key.code);
+                        KeyCode::Up => {
+                            self.select_line(self.akt_line.saturating_sub(1));
+                        }
+                        KeyCode::Down => {
+                            self.select_line(self.akt_line + 1);
                         }
+                        KeyCode::Right => {
+                            self.move_cursor(self.input_pointer + 1);
+                        }
+                        KeyCode::Left => {
+                            self.move_cursor(self.input_pointer.saturating_sub(1));
+                        }
+                        KeyCode::Home => {
+                            self.move_cursor(0);
+                        }
+                        KeyCode::End => {
+                            self.move_cursor(usize::MAX);
+                        }
+                        KeyCode::PageDown => {
+                            self.move_cursor(self.input_pointer.saturating_add(self.stride));
+                        }
+                        KeyCode::PageUp => {
+                            self.move_cursor(self.input_pointer.saturating_sub(self.stride));
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -126,164 +521,920 @@ impl App {
         Ok(())
     }
 
-    fn draw(&mut self, frame: &mut Frame) {
-        //        let command_string = "DLLARRUA";
-        let command_string = self.input_lines[self.akt_line].clone();
-        let commands: Vec<char> = command_string.chars().map(|c| c).collect();
-
-        let dir_button_signals = HashMap::from([
-            ((1, 0), 'U'),
-            ((2, 0), 'A'),
-            ((0, 1), 'L'),
-            ((1, 1), 'D'),
-            ((2, 1), 'R'),
-        ]);
-        let mut dir_signal_button = HashMap::new();
-        for (k, v) in dir_button_signals.iter() {
-            dir_signal_button.insert(*v, *k);
-        }
-        let dir_valid_signals = vec!['U', 'A', 'L', 'D', 'R'];
-        let num_button_signals = HashMap::from([
-            ((0, 0), '7'),
-            ((1, 0), '8'),
-            ((2, 0), '9'),
-            ((0, 1), '4'),
-            ((1, 1), '5'),
-            ((2, 1), '6'),
-            ((0, 2), '1'),
-            ((1, 2), '2'),
-            ((2, 2), '3'),
-            ((1, 3), '0'),
-            ((2, 3), 'A'),
-        ]);
-        let num_valid_signals = vec!['A', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
-        if self.key_pressed.is_some() {
-            self.input_pointer = match self.key_pressed.unwrap() {
-                KeyCode::Right => (self.input_pointer + 1).min(command_string.len() as i32),
-                KeyCode::Left => (self.input_pointer - 1).max(0),
-
-                _ => self.input_pointer,
-            };
-            self.key_pressed = None;
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => prompt.text.push(c),
+            KeyCode::Backspace => {
+                prompt.text.pop();
+            }
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                let prompt = self.prompt.take().expect("prompt is open");
+                match prompt.kind {
+                    PromptKind::Solve => self.solve_code(prompt.text.trim()),
+                    PromptKind::Game => self.start_game(prompt.text.trim()),
+                    PromptKind::Hint => self.show_hint(&prompt.text.trim().to_uppercase()),
+                    PromptKind::Autopilot => self.autopilot(&prompt.text.trim().to_uppercase()),
+                }
+            }
+            _ => {}
         }
+    }
 
-        self.pad_x = vec![2, 2, 2, 2];
-        self.pad_y = vec![0, 0, 0, 3];
-        self.pad_has_error = vec![false, false, false, false];
-        self.pad_just_moved_to = vec![false, false, false, false];
-        self.pad_just_moved_from = vec![None, None, None, None];
-        self.pad_just_pressed = vec![false, false, false, false];
+    fn handle_tree_key(&mut self, code: KeyCode) {
+        let Some(view) = self.tree.as_mut() else {
+            return;
+        };
+        let rows = view.rows();
+        let (id, _) = rows[view.selected];
+        match code {
+            KeyCode::Esc | KeyCode::Char('x') => self.tree = None,
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => view.selected = (view.selected + 1).min(rows.len() - 1),
+            KeyCode::Left => {
+                view.folded.insert(id);
+            }
+            KeyCode::Right => {
+                view.folded.remove(&id);
+            }
+            KeyCode::Enter => {
+                let step = view.tree.nodes[id].step;
+                self.tree = None;
+                self.move_cursor(step + 1);
+            }
+            KeyCode::Char('d') => self.export_tree(),
+            _ => {}
+        }
+    }
 
-        let mut pad_output: String = String::new();
+    fn handle_edit_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('z') if ctrl => self.undo_edit(true),
+            KeyCode::Char('y') if ctrl => self.undo_edit(false),
+            KeyCode::Char('s') if ctrl => self.save_line(false),
+            KeyCode::Char('n') if ctrl => self.save_line(true),
+            KeyCode::Esc | KeyCode::Char('i') => self.stop_editing(),
+            KeyCode::Char(c) if !ctrl => {
+                let label = from_arrow(c).to_ascii_uppercase();
+                if DIR_LABELS.contains(&label) {
+                    let at = self.input_pointer;
+                    self.edit(Edit::Insert {
+                        at,
+                        presses: label.to_string(),
+                    });
+                }
+            }
+            KeyCode::Backspace if self.input_pointer > 0 => {
+                let at = self.input_pointer - 1;
+                self.delete(at);
+            }
+            KeyCode::Delete if self.input_pointer < self.timeline.len() => {
+                self.delete(self.input_pointer);
+            }
+            KeyCode::Right => self.move_cursor(self.input_pointer + 1),
+            KeyCode::Left => self.move_cursor(self.input_pointer.saturating_sub(1)),
+            KeyCode::Home => self.move_cursor(0),
+            KeyCode::End => self.move_cursor(usize::MAX),
+            _ => {}
+        }
+    }
 
-        let mut signal: Option<&char>;
-        'a: for c in commands[0..self.input_pointer as usize].iter() {
-            if self.pad_has_error[0] {
-                break;
+    fn handle_record_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let label = match key.code {
+            KeyCode::Char('z') if ctrl => return self.undo_edit(true),
+            KeyCode::Char('y') if ctrl => return self.undo_edit(false),
+            KeyCode::Char('s') if ctrl => return self.save_line(false),
+            KeyCode::Esc | KeyCode::Char('m') => return self.stop_recording(),
+            KeyCode::Backspace if !self.timeline.is_empty() => {
+                return self.delete(self.timeline.len() - 1);
             }
+            KeyCode::Up => 'U',
+            KeyCode::Down => 'D',
+            KeyCode::Left => 'L',
+            KeyCode::Right => 'R',
+            KeyCode::Enter | KeyCode::Char(' ') => 'A',
+            KeyCode::Char('h') => return self.assist(PromptKind::Hint),
+            KeyCode::Char('a') => return self.assist(PromptKind::Autopilot),
+            _ => return,
+        };
+        let at = self.timeline.len();
+        self.edit(Edit::Insert {
+            at,
+            presses: label.to_string(),
+        });
+    }
 
-            self.pad_just_moved_from[0] = None;
-            self.pad_just_moved_to[0] = false;
-            self.pad_just_pressed[0] = false;
-            if dir_valid_signals.contains(c) {
-                (self.pad_x[0], self.pad_y[0]) = *dir_signal_button.get(&c).unwrap();
-                self.pad_just_pressed[0] = true;
-                signal = Some(c);
-            } else {
-                self.pad_has_error[0] = true;
-                break 'a;
-            }
-            for pad in 1..4 {
-                let last_pos = (self.pad_x[pad], self.pad_y[pad]);
-                self.pad_just_moved_to[pad] = false;
-                self.pad_just_moved_from[pad] = None;
-                self.pad_just_pressed[pad] = false;
-                if signal.is_some() {
-                    //                    println!("signal:{}", signal.unwrap());
-                    if !dir_valid_signals.contains(signal.unwrap()) {
-                        self.pad_has_error[pad] = true;
-                        break 'a;
-                    }
-                    let mut moved = false;
-                    match signal.unwrap() {
-                        'L' => {
-                            self.pad_x[pad] -= 1;
-                            if self.pad_x[pad] < 0
-                                || self.pad_invalid[pad] == (self.pad_x[pad], self.pad_y[pad])
-                            {
-                                self.pad_has_error[pad] = true;
-                                break 'a;
-                            }
-                            moved = true;
-                            signal = None;
-                        }
-                        'R' => {
-                            self.pad_x[pad] += 1;
-                            if self.pad_x[pad] > self.pad_max_x[pad]
-                                || self.pad_invalid[pad] == (self.pad_x[pad], self.pad_y[pad])
-                            {
-                                self.pad_has_error[pad] = true;
-                                break 'a;
-                            }
-                            moved = true;
-                            signal = None;
-                        }
-                        'U' => {
-                            self.pad_y[pad] -= 1;
-                            if self.pad_x[pad] < 0
-                                || self.pad_invalid[pad] == (self.pad_x[pad], self.pad_y[pad])
-                            {
-                                self.pad_has_error[pad] = true;
-                                break 'a;
-                            }
-                            moved = true;
-                            signal = None;
-                        }
-                        'D' => {
-                            self.pad_y[pad] += 1;
-                            if self.pad_y[pad] > self.pad_max_y[pad]
-                                || self.pad_invalid[pad] == (self.pad_x[pad], self.pad_y[pad])
-                            {
-                                self.pad_has_error[pad] = true;
-                                break 'a;
-                            }
-                            moved = true;
-                            signal = None;
-                        }
-                        'A' => {
-                            if pad == 3 {
-                                pad_output = pad_output
-                                    + num_button_signals
-                                        .get(&(self.pad_x[pad], self.pad_y[pad]))
-                                        .unwrap()
-                                        .to_string()
-                                        .as_str();
-                            }
+    /// Switch to `mode`, keeping the edit history of the selected line if it
+    /// was edited before.
+    fn start_editing(&mut self, mode: Mode) {
+        self.playing = false;
+        self.mode = mode;
+        self.editor();
+        self.message = "editing, ESC when done".to_string();
+    }
 
-                            self.pad_just_pressed[pad] = true;
-                            signal = match pad {
-                                0..=2 => Some(
-                                    dir_button_signals
-                                        .get(&(self.pad_x[pad], self.pad_y[pad]))
-                                        .unwrap(),
-                                ),
-                                3 => Some(
-                                    num_button_signals
-                                        .get(&(self.pad_x[pad], self.pad_y[pad]))
-                                        .unwrap(),
-                                ),
-                                _ => None,
-                            };
-                            moved = true;
-                        }
-                        _ => {}
-                    }
-                    if moved {
-                        self.pad_just_moved_to[pad] = true;
-                        self.pad_just_moved_from[pad] = Some(last_pos);
-                    }
+    /// Edit history of the selected line, started if there is none.
+    fn editor(&mut self) -> &mut Editor {
+        let line = &self.input_lines[self.akt_line];
+        self.edits.entry(self.akt_line).or_insert_with(|| Editor {
+            saved: line.clone(),
+            undo: vec![],
+            redo: vec![],
+        })
+    }
+
+    fn stop_editing(&mut self) {
+        self.mode = Mode::Browse;
+        self.message = if self.is_modified(self.akt_line) {
+            "changes not saved yet, i and ctrl-s save them".to_string()
+        } else {
+            String::new()
+        };
+    }
+
+    /// Add an empty line to the sequences and record into it.
+    fn start_recording(&mut self) {
+        self.input_lines.push(Sequence {
+            presses: String::new(),
+            code: None,
+            expect: None,
+            line: None,
+        });
+        self.checks.push(None);
+        self.input_count = self.input_lines.len();
+        self.akt_line = self.input_count - 1;
+        self.input_pointer = 0;
+        self.line_changed();
+        self.start_editing(Mode::Record);
+        self.message = "recording, arrows and Enter press the manual pad".to_string();
+    }
+
+    /// Record a new line that has to type `code`, or a random code if it is
+    /// empty.
+    fn start_game(&mut self, code: &str) {
+        let code = if code.is_empty() {
+            self.random_code()
+        } else {
+            code.to_uppercase()
+        };
+        match Solver::new(&self.layout, self.depth).length(&code) {
+            Ok(optimal) => {
+                self.start_recording();
+                self.message = format!("type {code} on the num pad, {optimal} presses are enough");
+                self.game = Some(Game {
+                    target: code,
+                    optimal,
+                    assisted: false,
+                    presses: 0,
+                });
+            }
+            Err(e) => self.message = format!("{code}: {e}"),
+        }
+    }
+
+    /// Three buttons of the num pad other than `A`, followed by `A`.
+    fn random_code(&self) -> String {
+        let buttons: Vec<char> = self
+            .layout
+            .numeric
+            .buttons()
+            .map(|(_, c)| c)
+            .filter(|c| *c != 'A')
+            .collect();
+        let mut seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(1, |d| d.as_nanos() as u64)
+            | 1;
+        let mut code = String::new();
+        while !buttons.is_empty() && code.len() < 3 {
+            // xorshift, good enough to pick a puzzle
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            code.push(buttons[seed as usize % buttons.len()]);
+        }
+        code.push('A');
+        code
+    }
+
+    /// Tell how the game goes after a press, and end it once the target is
+    /// typed.
+    fn check_game(&mut self) {
+        let Some(game) = &self.game else {
+            return;
+        };
+        let output = &self.timeline.state().output;
+        self.message = if self.line_fault.is_some() {
+            "a robot panicked, Backspace takes presses back".to_string()
+        } else if !game.target.starts_with(output.as_str()) {
+            format!(
+                "output {output} is off the target {}, Backspace takes presses back",
+                game.target
+            )
+        } else if *output == game.target {
+            return self.finish_game();
+        } else {
+            format!(
+                "typed \"{output}\" of {}, {} presses",
+                game.target,
+                self.timeline.len()
+            )
+        };
+    }
+
+    /// Score the finished game, add it to the score file and show the best
+    /// scores for the code.
+    fn finish_game(&mut self) {
+        let game = self.game.take().expect("a game is running");
+        let score = Score {
+            code: game.target.clone(),
+            depth: self.depth,
+            presses: game.presses,
+            optimal: game.optimal,
+        };
+        self.stop_recording();
+        let mut lines = vec![
+            format!(
+                "{} typed with {} presses, {} of them kept, the shortest sequence has {}",
+                score.code,
+                score.presses,
+                self.timeline.len(),
+                score.optimal
+            ),
+            format!("score {} of 1000", score.points()),
+            String::new(),
+        ];
+        let path = Path::new(SCORE_FILE);
+        let result = if game.assisted {
+            lines.insert(
+                2,
+                "hints or the autopilot were used, so it is not recorded".to_string(),
+            );
+            scores::load(path)
+        } else {
+            scores::append(path, &score).and_then(|()| scores::load(path))
+        };
+        match result {
+            Ok(all) => {
+                lines.push(format!(
+                    "best scores for {} at depth {}",
+                    score.code, self.depth
+                ));
+                lines.push(String::new());
+                let best = scores::best(&all, &score.code, self.depth, SCORES_SHOWN);
+                if best.is_empty() {
+                    lines.push("none yet".to_string());
+                }
+                for (i, s) in best.iter().enumerate() {
+                    lines.push(format!(
+                        "{:>3}. {:>8} presses {:>6}",
+                        i + 1,
+                        s.presses,
+                        s.points()
+                    ));
                 }
             }
+            Err(e) => lines.push(format!("{e:#}")),
+        }
+        self.info = Some(InfoPopup {
+            title: " game over ".to_string(),
+            lines,
+        });
+        self.message = "i and ctrl-s save the sequence to the input file".to_string();
+    }
+
+    /// Show a hint or run the autopilot, `kind` telling which, towards the
+    /// target of the game, the code of the selected line or the output
+    /// expected after `=>`. Asks for a code if there is none of them.
+    fn assist(&mut self, kind: PromptKind) {
+        let line = &self.input_lines[self.akt_line];
+        let target = match &self.game {
+            Some(game) => Some(game.target.clone()),
+            None => line.code.clone().or_else(|| {
+                line.expect
+                    .as_ref()
+                    .map(|e| e.output.clone())
+                    .filter(|o| !o.is_empty())
+            }),
+        };
+        match (target, kind) {
+            (Some(target), PromptKind::Hint) => self.show_hint(&target),
+            (Some(target), _) => self.autopilot(&target),
+            (None, kind) => {
+                self.prompt = Some(Prompt {
+                    kind,
+                    text: String::new(),
+                })
+            }
+        }
+    }
+
+    /// Arms of the chain at the cursor and what is left to type of `target`,
+    /// or why `target` cannot be typed from there.
+    fn remaining(&self, target: &str) -> Result<(Vec<char>, String), String> {
+        let state = self.timeline.state();
+        if state.fault.is_some() {
+            return Err("a robot panicked, there is no way on from here".to_string());
+        }
+        let Some(rest) = target.strip_prefix(state.output.as_str()) else {
+            return Err(format!(
+                "output {} is off the target {target}",
+                state.output
+            ));
+        };
+        if rest.is_empty() {
+            return Err(format!("{target} is typed already"));
+        }
+        let sim = self.timeline.simulator();
+        let arms = (0..sim.pad_count())
+            .map(|pad| {
+                sim.keypad(pad)
+                    .label(state.pads[pad].pos)
+                    .expect("arm rests on a button")
+            })
+            .collect();
+        Ok((arms, rest.to_string()))
+    }
+
+    /// Highlight the manual press that types `target` fastest from the
+    /// cursor.
+    fn show_hint(&mut self, target: &str) {
+        let (arms, rest) = match self.remaining(target) {
+            Ok(remaining) => remaining,
+            Err(e) => {
+                self.message = e;
+                return;
+            }
+        };
+        let mut solver = Solver::new(&self.layout, self.depth);
+        let result = solver.first_press(&arms, &rest).and_then(|first| {
+            let length = solver.length_from(&arms, &rest)?;
+            Ok((first.expect("something is left to type"), length))
+        });
+        match result {
+            Ok((first, length)) => {
+                self.hint = Some(first);
+                self.message = format!(
+                    "press {} next, {length} presses type the rest of {target}",
+                    display_label(first)
+                );
+                if let Some(game) = &mut self.game {
+                    game.assisted = true;
+                }
+            }
+            Err(e) => self.message = format!("{target}: {e}"),
+        }
+    }
+
+    /// Replace the presses after the cursor with the shortest presses that
+    /// type the rest of `target`.
+    fn autopilot(&mut self, target: &str) {
+        let (arms, rest) = match self.remaining(target) {
+            Ok(remaining) => remaining,
+            Err(e) => {
+                self.message = e;
+                return;
+            }
+        };
+        match Solver::new(&self.layout, self.depth).solve_from(&arms, &rest) {
+            Ok(presses) => {
+                if let Some(game) = &mut self.game {
+                    game.assisted = true;
+                }
+                let at = self.input_pointer;
+                let count = presses.len();
+                let old = self.input_lines[self.akt_line].presses[at..].to_string();
+                self.edit(Edit::Replace {
+                    at,
+                    old,
+                    new: presses,
+                });
+                if self.game.is_none() && self.info.is_none() {
+                    self.message = format!("autopilot finished {target} with {count} presses");
+                }
+            }
+            Err(e) => self.message = format!("{target}: {e}"),
+        }
+    }
+
+    /// Leave record mode, dropping the recorded line again if nothing was
+    /// pressed.
+    fn stop_recording(&mut self) {
+        let line = &self.input_lines[self.akt_line];
+        if line.presses.is_empty() && line.line.is_none() && self.akt_line == self.input_count - 1 {
+            self.input_lines.pop();
+            self.checks.pop();
+            self.edits.remove(&self.akt_line);
+            self.input_count -= 1;
+            self.akt_line -= 1;
+            self.line_changed();
+        }
+        self.stop_editing();
+        if self.game.take().is_some() {
+            self.message = "game given up".to_string();
+        }
+    }
+
+    /// Whether line `n` differs from what was read or last saved.
+    fn is_modified(&self, n: usize) -> bool {
+        self.edits
+            .get(&n)
+            .is_some_and(|e| e.saved.presses != self.input_lines[n].presses)
+    }
+
+    fn delete(&mut self, at: usize) {
+        let presses = self.input_lines[self.akt_line].presses[at..=at].to_string();
+        self.edit(Edit::Delete { at, presses });
+    }
+
+    /// Make `edit` to the selected line and record it for undo.
+    fn edit(&mut self, edit: Edit) {
+        let editor = self.editor();
+        editor.undo.push(edit.clone());
+        editor.redo.clear();
+        self.apply_edit(edit);
+    }
+
+    /// Take back the last edit of the selected line, or make the last one
+    /// taken back again if `undo` is false.
+    fn undo_edit(&mut self, undo: bool) {
+        let editor = self
+            .edits
+            .get_mut(&self.akt_line)
+            .expect("editing the line");
+        let (from, to) = if undo {
+            (&mut editor.undo, &mut editor.redo)
+        } else {
+            (&mut editor.redo, &mut editor.undo)
+        };
+        match from.pop() {
+            Some(edit) => {
+                to.push(edit.clone());
+                self.apply_edit(if undo { edit.inverse() } else { edit });
+            }
+            None => self.message = format!("nothing to {}", if undo { "undo" } else { "redo" }),
+        }
+    }
+
+    /// Change the presses of the selected line, put the cursor behind the
+    /// change and replay the line. Presses added count for a running game.
+    fn apply_edit(&mut self, edit: Edit) {
+        let line = &mut self.input_lines[self.akt_line];
+        let added = match &edit {
+            Edit::Insert { presses, .. } => presses.len(),
+            Edit::Delete { .. } => 0,
+            Edit::Replace { new, .. } => new.len(),
+        };
+        match edit {
+            Edit::Insert { at, presses } => {
+                line.presses.insert_str(at, &presses);
+                self.input_pointer = at + presses.len();
+            }
+            Edit::Delete { at, presses } => {
+                line.presses.replace_range(at..at + presses.len(), "");
+                self.input_pointer = at;
+            }
+            Edit::Replace { at, old, new } => {
+                line.presses.replace_range(at..at + old.len(), &new);
+                self.input_pointer = at + new.len();
+            }
+        }
+        if let Some(game) = &mut self.game {
+            game.presses += added;
+        }
+        // presses that were generated for a code stay tied to it only as
+        // long as they are unchanged
+        let saved = &self.edits[&self.akt_line].saved;
+        line.code = saved.code.clone().filter(|_| saved.presses == line.presses);
+        self.message = format!("{} presses", line.presses.len());
+        self.line_changed();
+        self.check_game();
+    }
+
+    /// Write the selected line back to the input file, or add it as a new
+    /// line if `as_new` is set or it is no sequence line of the file. A new
+    /// line is also added to the sequences, while the selected one goes back to
+    /// what it was. A recorded line that is in neither the file nor the
+    /// codes is saved in place, `as_new` would leave it behind empty.
+    fn save_line(&mut self, as_new: bool) {
+        let line = &self.input_lines[self.akt_line];
+        let saved = &self.edits[&self.akt_line].saved;
+        let as_new = as_new && (line.line.is_some() || saved.code.is_some());
+        let result = match line.line.filter(|_| !as_new && saved.code.is_none()) {
+            Some(n) => input::replace_sequence(&self.input_path, n, &saved.presses, &line.presses)
+                .map(|()| n),
+            None => input::append_sequence(&self.input_path, &line.presses),
+        };
+        let n = match result {
+            Ok(n) => n,
+            Err(e) => {
+                self.message = format!("{e}");
+                return;
+            }
+        };
+        let file = self.input_path.display().to_string();
+        if as_new {
+            let new = Sequence {
+                presses: line.presses.clone(),
+                code: None,
+                expect: None,
+                line: Some(n),
+            };
+            self.input_lines[self.akt_line] = saved.clone();
+            self.edits.remove(&self.akt_line);
+            self.input_lines.push(new);
+            self.checks.push(None);
+            self.input_count = self.input_lines.len();
+            self.akt_line = self.input_count - 1;
+            self.start_editing(Mode::Edit);
+            self.line_changed();
+            self.message = format!("saved as line {n} of {file}");
+        } else {
+            let line = &mut self.input_lines[self.akt_line];
+            line.line = Some(n);
+            line.code = None;
+            let editor = self
+                .edits
+                .get_mut(&self.akt_line)
+                .expect("editing the line");
+            editor.saved = line.clone();
+            self.message = format!("saved to line {n} of {file}");
+        }
+    }
+
+    /// Show the expansion of the last press on the pad selected with `k`,
+    /// the num pad if the manual pad is selected, up to the cursor.
+    fn open_tree(&mut self) {
+        let pad_count = self.timeline.simulator().pad_count();
+        let pad = match self.jump_level {
+            0 => pad_count - 1,
+            level => level,
+        };
+        // the tree only reaches back to the previous press on its pad, so
+        // only the presses from there on are traced
+        let presses = |events: &[TraceEvent]| {
+            events
+                .iter()
+                .any(|e| matches!(e, TraceEvent::Pressed { pad: p, .. } if *p == pad))
+        };
+        let root = if presses(self.timeline.simulator().events()) {
+            Some(self.input_pointer)
+        } else {
+            self.timeline.find_prev(presses)
+        };
+        let tree = root.and_then(|root| {
+            self.timeline.seek(root);
+            let previous = self.timeline.find_prev(presses);
+            self.timeline.seek(self.input_pointer);
+            let events = self.timeline.trace(previous.map_or(0, |p| p - 1)..root);
+            Tree::build(&events, pad_count, pad, root - 1)
+        });
+        match tree {
+            Some(tree) => {
+                let folded = if tree.nodes.len() > TREE_UNFOLD_LIMIT {
+                    (1..tree.nodes.len()).collect()
+                } else {
+                    HashSet::new()
+                };
+                self.tree = Some(TreeView {
+                    tree,
+                    folded,
+                    selected: 0,
+                });
+            }
+            None => {
+                self.message = format!("no press on {} up to the cursor", pad_name(pad, pad_count))
+            }
+        }
+    }
+
+    /// Write the open expansion tree as Graphviz dot file.
+    fn export_tree(&mut self) {
+        let Some(view) = &self.tree else {
+            return;
+        };
+        let pad_count = self.timeline.simulator().pad_count();
+        let root = &view.tree.nodes[0];
+        let filename = format!(
+            "keysim-tree-{}-{}-{}.dot",
+            self.akt_line + 1,
+            short_pad_name(root.pad, pad_count),
+            root.step + 1
+        );
+        let result = File::create(&filename).and_then(|f| {
+            view.tree
+                .write_dot(pad_count, |pad| pad_name(pad, pad_count), BufWriter::new(f))
+        });
+        self.message = match result {
+            Ok(()) => format!("expansion tree written to {filename}"),
+            Err(e) => format!("cannot write {filename}: {e}"),
+        };
+    }
+
+    /// Append a shortest sequence for `code` to the input lines and select it.
+    fn solve_code(&mut self, code: &str) {
+        let code = code.to_uppercase();
+        let depth = self.depth;
+        match Solver::new(&self.layout, depth).solve(&code) {
+            Ok(sequence) => {
+                self.message = format!("{code}: {} presses at depth {depth}", sequence.len());
+                self.input_lines.push(Sequence {
+                    presses: sequence,
+                    code: Some(code.clone()),
+                    expect: None,
+                    line: None,
+                });
+                self.checks.push(None);
+                self.input_count = self.input_lines.len();
+                self.akt_line = self.input_count - 1;
+                self.input_pointer = 0;
+                self.line_changed();
+            }
+            Err(e) => self.message = format!("{code}: {e}"),
+        }
+    }
+
+    /// Show sequence length and complexity of every code in the input file,
+    /// at the current depth and for both parts of the puzzle.
+    fn show_complexity(&mut self) {
+        let depth = self.depth;
+        let mut lines = vec![
+            format!("{:<8}{:>24}{:>24}", "code", "length", "complexity"),
+            String::new(),
+        ];
+        let mut solver = Solver::new(&self.layout, depth);
+        for code in &self.codes {
+            match solver.length(code).and_then(|length| {
+                let complexity = solver.complexity(code)?;
+                Ok((length, complexity))
+            }) {
+                Ok((length, complexity)) => {
+                    lines.push(format!("{code:<8}{length:>24}{complexity:>24}"));
+                }
+                Err(e) => lines.push(format!("{code:<8}  {e}")),
+            }
+        }
+        lines.push(String::new());
+        for (title, d) in [
+            (format!("sum at depth {depth}"), depth),
+            ("part 1, depth 2".to_string(), 2),
+            ("part 2, depth 25".to_string(), 25),
+        ] {
+            let total = Solver::new(&self.layout, d)
+                .total_complexity(&self.codes)
+                .map_or_else(|e| e.to_string(), |t| t.to_string());
+            lines.push(format!("{title:<32}{total:>24}"));
+        }
+        if self.codes.is_empty() {
+            lines = vec!["no numeric codes like 029A in the input file".to_string()];
+        }
+        self.info = Some(InfoPopup {
+            title: " complexity ".to_string(),
+            lines,
+        });
+    }
+
+    /// List the lines of the input file that were skipped and why.
+    fn show_load_report(&mut self) {
+        let mut lines = vec![
+            format!(
+                "{}: {} sequences, {} codes, {} lines rejected",
+                self.input_path.display(),
+                self.input_count,
+                self.codes.len(),
+                self.rejected.len()
+            ),
+            String::new(),
+        ];
+        for r in &self.rejected {
+            let text: String = r.text.chars().take(40).collect();
+            lines.push(format!("line {:<6}{:<42}{}", r.line, text, r.reason));
+        }
+        self.info = Some(InfoPopup {
+            title: " load report ".to_string(),
+            lines,
+        });
+    }
+
+    /// Write the events of the whole selected line to a JSON Lines file.
+    fn export_trace(&mut self) {
+        let mut sim = Simulator::with_layout(&self.layout, self.depth);
+        let events = sim.trace(&self.input_lines[self.akt_line].presses);
+        let filename = format!("keysim-trace-{}.jsonl", self.akt_line + 1);
+        let result = File::create(&filename).and_then(|f| write_jsonl(&events, BufWriter::new(f)));
+        self.message = match result {
+            Ok(()) => format!("{} events written to {filename}", events.len()),
+            Err(e) => format!("cannot write {filename}: {e}"),
+        };
+    }
+
+    /// Rebuild the chain with `depth` robot pads, at most [`MAX_DEPTH`] with a
+    /// message if more are asked for, generate the sequences for codes again
+    /// and replay the selected line.
+    fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.min(MAX_DEPTH);
+        let mut solver = Solver::new(&self.layout, self.depth);
+        for (i, line) in self.input_lines.iter_mut().enumerate() {
+            if let Some(code) = &line.code {
+                *line = Sequence::for_code_or_empty(&mut solver, code, line.line);
+                self.edits.remove(&i);
+            }
+        }
+        self.check_lines();
+        self.jump_level = self.jump_level.min(self.depth + 1);
+        self.line_changed();
+        if depth > MAX_DEPTH {
+            self.message = format!("depth {depth} is too deep to show, using {MAX_DEPTH}");
+        }
+    }
+
+    /// Put the cursor at `position`, clamped to the selected line.
+    fn move_cursor(&mut self, position: usize) {
+        self.input_pointer = position.min(self.timeline.len());
+        self.update_sim();
+    }
+
+    /// Move the cursor to the next or previous press whose events match
+    /// `pred`, `what` names the press in the message if there is none.
+    fn jump_to<F: Fn(&[TraceEvent]) -> bool>(&mut self, forward: bool, what: &str, pred: F) {
+        let found = if forward {
+            self.timeline.find_next(pred)
+        } else {
+            self.timeline.find_prev(pred)
+        };
+        match found {
+            Some(position) => self.move_cursor(position),
+            None => {
+                let direction = if forward { "after" } else { "before" };
+                self.message = format!("no {what} {direction} the cursor");
+            }
+        }
+    }
+
+    /// Move the cursor just past the press that makes a robot panic.
+    fn jump_to_fault(&mut self) {
+        match &self.line_fault {
+            Some(fault) => {
+                self.input_pointer = fault.step + 1;
+                self.update_sim();
+            }
+            None => self.message = "no robot panics in this string".to_string(),
+        }
+    }
+
+    /// Select line `n` if there is one, keeping the cursor where it is as far
+    /// as the new line allows.
+    fn select_line(&mut self, n: usize) {
+        if n < self.input_count && n != self.akt_line {
+            self.akt_line = n;
+            self.input_pointer = self.input_pointer.min(self.input_lines[n].presses.len());
+            self.line_changed();
+        }
+    }
+
+    /// Start a new timeline for the selected line, look for a fault in the
+    /// whole line, then move to the cursor.
+    fn line_changed(&mut self) {
+        let sim = Simulator::with_layout(&self.layout, self.depth);
+        self.timeline = Timeline::new(sim, &self.input_lines[self.akt_line].presses);
+        self.line_fault = self.timeline.seek(self.timeline.len()).fault.clone();
+        if let Some(expect) = &self.input_lines[self.akt_line].expect {
+            let failures = expect.failures(self.timeline.simulator());
+            self.checks[self.akt_line] = Some(failures.is_empty());
+            if !failures.is_empty() {
+                self.message = format!("assertion fails: {}", failures.join(", "));
+            }
+        }
+        self.update_sim();
+    }
+
+    /// Run every line that has an assertion and note whether it holds.
+    fn check_lines(&mut self) {
+        let mut sim = Simulator::with_layout(&self.layout, self.depth);
+        self.checks = self
+            .input_lines
+            .iter()
+            .map(|line| {
+                let expect = line.expect.as_ref()?;
+                sim.reset();
+                sim.run(&line.presses);
+                Some(expect.failures(&sim).is_empty())
+            })
+            .collect();
+    }
+
+    /// Move the chain to the cursor.
+    fn update_sim(&mut self) {
+        self.timeline.seek(self.input_pointer);
+        self.hint = None;
+    }
+
+    /// Smallest terminal the compact layout fits in.
+    fn min_size(&self) -> (u16, u16) {
+        let (width, height) = pad_size(self.layout.max_width(), self.layout.max_height(), true);
+        let fault = if self.line_fault.is_some() { 4 } else { 0 };
+        (width.max(MIN_NAV_WIDTH) + 2, height + 4 + 4 + fault + 2)
+    }
+
+    /// Layout of the pads in `width` x `height`, which has to hold the output
+    /// column too. Tries full buttons with the output column next to the
+    /// pads, then compact buttons, then the output column below them, then
+    /// more rows of pads. Only if all pads still do not fit, shows the rows
+    /// that fit from the pad scroll on.
+    fn pad_layout(&mut self, width: u16, height: u16) -> PadLayout {
+        let sim = self.timeline.simulator();
+        let pad_count = sim.pad_count();
+        let sizes = |compact| -> Vec<(u16, u16)> {
+            (0..pad_count)
+                .map(|pad| {
+                    let keypad = sim.keypad(pad);
+                    let (width, height) = pad_size(keypad.width(), keypad.height(), compact);
+                    let title = pad_name(pad, pad_count).len() as u16 + 2;
+                    (width.max(title), height)
+                })
+                .collect()
+        };
+        for compact in [false, true] {
+            let sizes = sizes(compact);
+            let rows = wrap_pads(&sizes, 0..pad_count, width.saturating_sub(INFO_WIDTH + 3));
+            if rows.len() == 1 && pads_height(&sizes, &rows).max(9) <= height {
+                return PadLayout {
+                    compact,
+                    info_stacked: false,
+                    rows,
+                    sizes,
+                };
+            }
+        }
+        let sizes = sizes(true);
+        let height = height.saturating_sub(4);
+        let visible = |from: usize| {
+            let mut rows = wrap_pads(&sizes, from..pad_count, width);
+            while rows.len() > 1 && pads_height(&sizes, &rows) > height {
+                rows.pop();
+            }
+            rows
+        };
+        let ends = |rows: &[Range<usize>]| rows.last().is_some_and(|r| r.end == pad_count);
+        self.pad_scroll = self.pad_scroll.min(pad_count - 1);
+        let mut rows = visible(self.pad_scroll);
+        while self.pad_scroll > 0 && ends(&rows) {
+            let earlier = visible(self.pad_scroll - 1);
+            if !ends(&earlier) {
+                break;
+            }
+            self.pad_scroll -= 1;
+            rows = earlier;
+        }
+        PadLayout {
+            compact: true,
+            info_stacked: true,
+            rows,
+            sizes,
+        }
+    }
+
+    /// What every pad pressed during the manual presses in `range`, one row
+    /// per pad with each button under the manual press that caused it, and
+    /// how many buttons every pad pressed before `range`.
+    fn level_rows(&mut self, range: Range<usize>) -> (Vec<Vec<Option<char>>>, Vec<usize>) {
+        let position = self.timeline.position();
+        let before = self
+            .timeline
+            .seek(range.start)
+            .pads
+            .iter()
+            .map(|p| p.presses)
+            .collect();
+        self.timeline.seek(position);
+        let pad_count = self.timeline.simulator().pad_count();
+        let mut rows = vec![vec![None; range.len()]; pad_count];
+        for e in self.timeline.trace(range.clone()) {
+            if let TraceEvent::Pressed { step, pad, label } = e {
+                rows[pad][step - range.start] = Some(label);
+            }
+        }
+        (rows, before)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        let (min_width, min_height) = self.min_size();
+        if area.width < min_width || area.height < min_height {
+            let text = format!(
+                "terminal too small, need {min_width}x{min_height}, have {}x{}",
+                area.width, area.height
+            );
+            frame.render_widget(
+                Paragraph::new(text).wrap(Wrap { trim: true }).centered(),
+                centered_rect(&area, area.width, 2),
+            );
+            return;
         }
 
         /////////////////////////////////////////////////
@@ -292,121 +1443,308 @@ impl App {
 
         let empty_paragraph = Paragraph::new("");
 
-        // layout main areas
-
-        let [input_area, pad_area, nav_area] = Layout::vertical([
-            Constraint::Length(3),
-            Constraint::Length(14),
-            Constraint::Length(3),
-        ])
-        .spacing(1)
-        .areas(inner_rect(&frame.area()));
+        // layout main areas: the sequence list goes if it leaves too little
+        // room, the pads shrink, stack and wrap until all of them fit, see
+        // pad_layout, and the rows under the nav bar take what height is left
 
-        //layout help popup
-
-        let popup_rect: Rect = Rect {
-            x: frame.area().x + 2,
-            y: frame.area().y + 2,
-            width: frame.area().width - 4,
-            height: frame.area().height - 4,
+        let inner = inner_rect(&area);
+        let pad_count = self.timeline.simulator().pad_count();
+        let show_list = self.show_list && inner.width >= LIST_WIDTH + 1 + MIN_MAIN_WIDTH;
+        let [list_area, main_area] = if show_list {
+            Layout::horizontal([Constraint::Length(LIST_WIDTH), Constraint::Fill(1)])
+                .spacing(1)
+                .areas(inner)
+        } else {
+            [Rect::default(), inner]
+        };
+        let fault_height = if self.line_fault.is_some() { 4 } else { 0 };
+        let below_pads = |fault_height: u16| {
+            4 + if fault_height > 0 {
+                fault_height + 1
+            } else {
+                0
+            }
+        };
+        let pad_layout = self.pad_layout(
+            main_area.width,
+            main_area
+                .height
+                .saturating_sub(below_pads(fault_height.min(3))),
+        );
+        let PadLayout {
+            compact,
+            info_stacked,
+            ref rows,
+            ref sizes,
+        } = pad_layout;
+        let rows_height = pads_height(sizes, rows);
+        let block_height = if info_stacked {
+            rows_height + 4
+        } else {
+            rows_height.max(9)
+        };
+        let fault_height = if block_height + below_pads(fault_height) > main_area.height {
+            fault_height.min(3)
+        } else {
+            fault_height
+        };
+        let spare = main_area
+            .height
+            .saturating_sub(block_height + below_pads(fault_height)) as usize;
+        let level_count = if self.show_levels {
+            (pad_count - 1).min(MAX_LEVEL_ROWS).min(spare)
+        } else {
+            0
+        };
+        let mut constraints = vec![
+            Constraint::Length(block_height),
+            Constraint::Length(level_count as u16 + 3),
+        ];
+        if fault_height > 0 {
+            constraints.push(Constraint::Length(fault_height));
+        }
+        let main_rects = Layout::vertical(constraints).spacing(1).split(main_area);
+        let (block_area, nav_area) = (main_rects[0], main_rects[1]);
+        let fault_area = main_rects.get(2).copied().unwrap_or_default();
+        let [pad_area, info_area] = if info_stacked {
+            Layout::vertical([Constraint::Length(rows_height), Constraint::Length(3)])
+                .spacing(1)
+                .areas(block_area)
+        } else {
+            [block_area, Rect::default()]
         };
 
-        // layout input buttons
+        // pads below the manual pad that get a row under the nav bar, following
+        // the pad scroll when they do not all fit
 
-        let input_buttons: Vec<Rect> = Layout::horizontal([Constraint::Length(7); 10])
-            .split(input_area)
-            .to_vec();
+        let first_level = self.pad_scroll.clamp(1, pad_count - level_count.max(1));
 
-        // layout pads
+        // manual presses shown in the nav bar, scrolled to keep the cursor
+        // away from the edges, between the pad names and two ellipsis slots
 
-        let pads_rect = Layout::horizontal([Constraint::Length(25); 5])
-            .spacing(3)
-            .split(pad_area);
+        let columns = nav_area.width.saturating_sub(LEVEL_LABEL_WIDTH + 4).max(1) as usize;
+        let margin = (columns / 4).min(NAV_MARGIN);
+        let cursor = self.input_pointer.saturating_sub(1);
+        if cursor < self.nav_scroll + margin {
+            self.nav_scroll = cursor.saturating_sub(margin);
+        } else if cursor + margin >= self.nav_scroll + columns {
+            self.nav_scroll = cursor + margin + 1 - columns;
+        }
+        self.nav_scroll = self
+            .nav_scroll
+            .min(self.timeline.len().saturating_sub(columns));
+        let nav_end = (self.nav_scroll + columns).min(self.timeline.len());
+        let (level_rows, presses_before) = self.level_rows(self.nav_scroll..nav_end);
 
-        // layout output and steps
+        let command_string = self.input_lines[self.akt_line].presses.clone();
+        let state = self.timeline.state();
 
-        let [output_rect, steps_rect] =
-            Layout::vertical([Constraint::Length(3); 2]).areas(pads_rect[4]);
+        //layout help popup
 
-        // layout buttons
+        let popup_rect: Rect = Rect {
+            x: area.x + 2,
+            y: area.y + 2,
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(4),
+        };
 
-        let two_rows = Layout::vertical([Constraint::Length(3); 2]).margin(1);
-        let four_rows = Layout::vertical([Constraint::Length(3); 4]).margin(1);
-        let three_cols = Layout::horizontal([Constraint::Length(7); 3]).horizontal_margin(1);
+        // layout pads row by row, every box as tall as its buttons, with the
+        // output column after the pads of a single row
 
-        let mut buttons: Vec<Rect> = vec![];
-        for i in 0..4 {
-            let rows;
-            if i < 3 {
-                rows = two_rows.clone();
-            } else {
-                rows = four_rows.clone();
+        let sim = self.timeline.simulator();
+        let row_areas = Layout::vertical(
+            rows.iter()
+                .map(|row| Constraint::Min(pads_height(sizes, slice::from_ref(row)))),
+        )
+        .spacing(1)
+        .split(pad_area);
+        let mut pad_rects = vec![];
+        let mut column_area = Rect::default();
+        for (row, row_area) in rows.iter().zip(row_areas.iter()) {
+            let constraints = sizes[row.clone()]
+                .iter()
+                .map(|(width, _)| Constraint::Length(*width))
+                .chain((!info_stacked).then_some(Constraint::Length(INFO_WIDTH)));
+            let rects = Layout::horizontal(constraints).spacing(3).split(*row_area);
+            for (pad, rect) in row.clone().zip(rects.iter()) {
+                let height = sizes[pad].1.min(rect.height);
+                pad_rects.push((pad, Rect { height, ..*rect }));
             }
-            for row in rows.split(pads_rect[i]).iter() {
-                let button_row = three_cols.split(*row);
-                for b in button_row.iter() {
-                    buttons.push(b.clone());
-                }
+            if !info_stacked {
+                column_area = rects[row.len()];
             }
         }
+        let shown = rows.first().map_or(0, |r| r.start)..rows.last().map_or(0, |r| r.end);
+
+        // layout output, steps and depth, in a column next to the pads or in
+        // a row below them
+
+        let [output_rect, steps_rect, depth_rect] = if info_stacked {
+            Layout::horizontal([
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+            ])
+            .areas(info_area)
+        } else {
+            Layout::vertical([Constraint::Length(3); 3]).areas(column_area)
+        };
 
         // render outer frame
 
-        let block = Block::bordered()
-            .title(Line::from("  Keypad Sim 2024  ").centered())
-            .title(Line::from(" help: ?   quit q ").right_aligned());
+        let mut block = Block::bordered().title(Line::from("  Keypad Sim 2024  ").centered());
+        if area.width >= MIN_MAIN_WIDTH {
+            block = block.title(Line::from(" help: ?   quit q ").right_aligned());
+        }
+        if !self.message.is_empty() {
+            block = block.title_bottom(Line::from(format!(" {} ", self.message)));
+        }
         frame.render_widget(empty_paragraph.clone().block(block), frame.area());
 
-        // render input buttons
+        // render sequence list, scrolled to keep the selected line visible
 
-        let inactive_line_style = Style::new().white().on_black().bold();
-        let active_line_style = Style::new().black().on_green().bold();
-        let mut style;
-        for i in 0..9 {
-            if i < self.input_count {
-                let text = (i + 1).to_string();
-                if i == self.akt_line {
-                    style = active_line_style;
-                } else {
-                    style = inactive_line_style;
-                }
-                render_button(
-                    "".to_string(),
-                    &Paragraph::new(text).centered(),
-                    Borders::ALL,
-                    frame,
-                    input_buttons[i],
-                    style,
-                );
+        if show_list {
+            let list_height = list_area.height.saturating_sub(2) as usize;
+            if self.akt_line < self.line_scroll {
+                self.line_scroll = self.akt_line;
+            } else if self.akt_line >= self.line_scroll + list_height {
+                self.line_scroll = self.akt_line + 1 - list_height;
             }
+            let number_width = self.input_count.to_string().len();
+            let preview_width = (LIST_WIDTH as usize).saturating_sub(number_width + 13);
+            let inactive_line_style = Style::new().white().on_black();
+            let active_line_style = Style::new().black().on_green().bold();
+            let list_lines: Vec<Line> = self
+                .input_lines
+                .iter()
+                .enumerate()
+                .skip(self.line_scroll)
+                .take(list_height)
+                .map(|(i, line)| {
+                    let len = line.presses.len();
+                    let text = match &line.code {
+                        Some(code) if len == 0 => format!("{code} too long"),
+                        Some(code) => format!(
+                            "{code}  c={}",
+                            numeric_part(code).saturating_mul(len as u128)
+                        ),
+                        None => line.presses.chars().take(preview_width).collect(),
+                    };
+                    let badge = match self.checks.get(i).copied().flatten() {
+                        Some(true) => "✓".green(),
+                        Some(false) => "✗".red(),
+                        None => " ".into(),
+                    };
+                    let style = if i == self.akt_line {
+                        active_line_style
+                    } else {
+                        inactive_line_style
+                    };
+                    let modified = if self.is_modified(i) { '*' } else { ' ' };
+                    Line::from(vec![
+                        badge.bold(),
+                        format!("{modified}{:>number_width$} {len:>6} {text}", i + 1).into(),
+                    ])
+                    .style(style)
+                })
+                .collect();
+            let list_title = format!("sequences {}/{}", self.akt_line + 1, self.input_count);
+            frame.render_widget(
+                Paragraph::new(list_lines).block(Block::bordered().title(list_title)),
+                list_area,
+            );
         }
 
-        // render pads
+        // render pads and their buttons
 
         let box_normal_style = Style::new().white().on_black();
         let box_error_style = Style::new().black().on_red();
-        let pad_boxes_names = vec!["manual pad", "second pad", "third pad", "num pad"];
-        let mut style;
-        for i in 0..4 {
-            if self.pad_has_error[i] {
-                style = box_error_style;
+
+        let normal_style = Style::new().white().on_black().bold();
+        let pos_style = Style::new().black().on_green().bold();
+        let pressed = Style::new().black().on_red().bold();
+        let moved_from = Style::new().green().on_black().bold();
+        let hint_style = Style::new().black().on_yellow().bold();
+
+        for &(pad, pad_rect) in &pad_rects {
+            let pad_state = &state.pads[pad];
+            let style = if pad_state.has_error {
+                box_error_style
             } else {
-                style = box_normal_style;
+                box_normal_style
             };
             render_box(
-                pad_boxes_names[i].to_string(),
+                pad_name(pad, pad_count),
                 Borders::ALL,
                 style,
                 frame,
-                pads_rect[i],
+                pad_rect,
             );
+
+            let keypad = sim.keypad(pad);
+            let (button_width, button_height) = if compact { (3, 1) } else { (7, 3) };
+            let rows = Layout::vertical(vec![
+                Constraint::Length(button_height);
+                keypad.height() as usize
+            ])
+            .margin(1);
+            let cols = Layout::horizontal(vec![
+                Constraint::Length(button_width);
+                keypad.width() as usize
+            ])
+            .horizontal_margin(1);
+            let buttons = rows
+                .split(pad_rect)
+                .iter()
+                .flat_map(|row| cols.split(*row).to_vec())
+                .collect::<Vec<Rect>>();
+            let index = |(x, y): (i32, i32)| (x + y * keypad.width()) as usize;
+            for (i, b) in buttons.iter().enumerate() {
+                let pos = (i as i32 % keypad.width(), i as i32 / keypad.width());
+                let text = keypad
+                    .label(pos)
+                    .map(|c| {
+                        if pad < pad_count - 1 {
+                            display_label(c)
+                        } else {
+                            c
+                        }
+                    })
+                    .map(String::from)
+                    .unwrap_or_default();
+                let style = if pad == 0 && self.hint.is_some() && self.hint == keypad.label(pos) {
+                    hint_style
+                } else if i == index(pad_state.pos) {
+                    if pad_state.just_pressed {
+                        pressed
+                    } else {
+                        pos_style
+                    }
+                } else if pad_state.just_moved_from.map(index) == Some(i) {
+                    moved_from
+                } else {
+                    normal_style
+                };
+                if compact {
+                    frame.render_widget(Paragraph::new(text).centered().style(style), *b);
+                    continue;
+                }
+                render_button(
+                    "".to_string(),
+                    &Paragraph::new(text).centered(),
+                    Borders::ALL,
+                    frame,
+                    *b,
+                    style,
+                );
+            }
         }
 
         // render output
 
         render_borders(
             "output".to_string(),
-            &Paragraph::new(pad_output),
+            &Paragraph::new(state.output.as_str()),
             Borders::ALL,
             frame,
             output_rect,
@@ -416,109 +1754,229 @@ impl App {
 
         render_borders(
             "steps".to_string(),
-            &Paragraph::new(self.input_pointer.to_string()),
+            &Paragraph::new(format!(
+                "{} / {}   stride {}",
+                self.input_pointer,
+                self.timeline.len(),
+                self.stride
+            )),
             Borders::ALL,
             frame,
             steps_rect,
         );
 
-        // render buttons
+        // render depth, and which pads are on screen if not all of them fit
 
-        let dir_labels = vec!["", "^", "A", "<", "v", ">"];
-        let num_labels = vec!["7", "8", "9", "4", "5", "6", "1", "2", "3", "", "0", "A"];
+        let mut depth_text = self.depth.to_string();
+        if shown.len() < pad_count {
+            depth_text += &format!("  pads {}-{}/{}", shown.start + 1, shown.end, pad_count);
+        }
+        render_borders(
+            "depth".to_string(),
+            &Paragraph::new(depth_text),
+            Borders::ALL,
+            frame,
+            depth_rect,
+        );
 
-        let normal_style = Style::new().white().on_black().bold();
-        let pos_style = Style::new().black().on_green().bold();
-        let pressed = Style::new().black().on_red().bold();
-        let moved_from = Style::new().green().on_black().bold();
+        // render nav: the manual presses, and under them what the pads below
+        // pressed so far. Every row is colored by segment, a segment of pad k
+        // being the manual presses that make pad k press once, so the segments
+        // of a row nest in those of the rows below. The manual presses follow
+        // the segments of the first robot pad, which end at every A. The
+        // segments holding the cursor are highlighted. An ellipsis marks
+        // presses scrolled out of view.
 
-        let mut style;
-        for (i, b) in buttons.iter().enumerate() {
-            let text;
-            let pad: usize = (i / 6).min(3);
-            if i < 18 {
-                text = dir_labels[i % 6];
+        let mode_title = match self.mode {
+            Mode::Browse => None,
+            Mode::Edit => Some((
+                " edit ",
+                " ctrl-z undo  ctrl-y redo  ctrl-s save  ctrl-n save as new ",
+            )),
+            Mode::Record => Some((
+                " record ",
+                " arrows and Enter press  Backspace takes back  ctrl-s save ",
+            )),
+        };
+        if let Some((title, keys)) = mode_title {
+            let (title, border_style) = match &self.game {
+                Some(game)
+                    if game.target.starts_with(state.output.as_str())
+                        && self.line_fault.is_none() =>
+                {
+                    (
+                        format!(" game: type {} ", game.target),
+                        Style::new().yellow(),
+                    )
+                }
+                Some(game) => (
+                    format!(" game: type {}, off target ", game.target),
+                    Style::new().red(),
+                ),
+                None => (title.to_string(), Style::new().yellow()),
+            };
+            let block = Block::bordered()
+                .title(title)
+                .title(Line::from(keys).right_aligned())
+                .border_style(border_style);
+            frame.render_widget(block, nav_area);
+        }
+        let label_style = Style::new().dark_gray();
+        let cursor_style = Style::new().black().on_green();
+        let cursor_column = self
+            .input_pointer
+            .checked_sub(1)
+            .and_then(|c| c.checked_sub(self.nav_scroll));
+        let row_rect = |row: u16| Rect {
+            x: nav_area.x + 1,
+            y: nav_area.y + 1 + row,
+            width: nav_area.width.saturating_sub(2),
+            height: 1,
+        };
+        let segments: Vec<Vec<usize>> = level_rows
+            .iter()
+            .zip(&presses_before)
+            .map(|(row, before)| {
+                let mut count = *before;
+                row.iter()
+                    .map(|c| {
+                        let segment = count;
+                        count += c.is_some() as usize;
+                        segment
+                    })
+                    .collect()
+            })
+            .collect();
+        let segment_style = |pad: usize, column: usize| {
+            let segment = segments[pad][column];
+            if cursor_column.is_some_and(|c| segments[pad].get(c) == Some(&segment)) {
+                Style::new().white().on_blue()
+            } else if segment.is_multiple_of(2) {
+                Style::new().white().on_black()
             } else {
-                text = num_labels[i - 18];
+                Style::new().white().bg(Color::Indexed(237))
             }
+        };
+        let label = |pad: usize| {
+            Span::styled(
+                format!(
+                    "{:<w$}",
+                    short_pad_name(pad, pad_count),
+                    w = LEVEL_LABEL_WIDTH as usize
+                ),
+                label_style,
+            )
+        };
+        let start = match (self.nav_scroll, self.input_pointer) {
+            (0, 0) => Span::styled(" ", cursor_style),
+            (0, _) => Span::raw(" "),
+            _ => Span::styled("…", label_style),
+        };
+        let end = if nav_end < self.timeline.len() {
+            Span::styled("…", label_style)
+        } else {
+            Span::raw("")
+        };
 
-            if i as i32 == pad as i32 * 6 + self.pad_x[pad] + self.pad_y[pad] * 3 {
-                if self.pad_just_pressed[pad] {
-                    style = pressed;
-                } else {
-                    style = pos_style;
-                }
+        let mut spans = vec![label(0), start.clone()];
+        for (column, c) in command_string[self.nav_scroll..nav_end].chars().enumerate() {
+            let style = if Some(column) == cursor_column {
+                cursor_style
             } else {
-                if self.pad_just_moved_from[pad].is_some()
-                    && i as i32 - pad as i32 * 6
-                        == self.pad_just_moved_from[pad].unwrap().0
-                            + self.pad_just_moved_from[pad].unwrap().1 * 3
-                {
-                    style = moved_from;
+                segment_style(1, column)
+            };
+            spans.push(Span::styled(c.to_string(), style));
+        }
+        spans.push(end.clone());
+        frame.render_widget(Paragraph::new(Line::from(spans)), row_rect(0));
+
+        for (row, pad) in (first_level..first_level + level_count).enumerate() {
+            let mut spans = vec![label(pad), start.clone().style(Style::new())];
+            for (column, c) in level_rows[pad].iter().enumerate() {
+                let pressed = self.nav_scroll + column < self.input_pointer;
+                let c = c.filter(|_| pressed).unwrap_or(' ');
+                let style = if Some(column) == cursor_column && c != ' ' {
+                    cursor_style
                 } else {
-                    style = normal_style;
-                }
+                    segment_style(pad, column)
+                };
+                spans.push(Span::styled(c.to_string(), style));
             }
-            render_button(
-                "".to_string(),
-                &Paragraph::new(text).centered(),
-                Borders::ALL,
-                frame,
-                *b,
-                style,
-            );
+            spans.push(end.clone());
+            frame.render_widget(Paragraph::new(Line::from(spans)), row_rect(row as u16 + 1));
         }
 
-        // render nav
+        // render which presses the nav bar shows if the line does not fit
 
-        let nav_string = " ".to_string() + command_string.as_str();
-
-        let mut pre_cursor = "";
-        if self.input_pointer > 0 {
-            pre_cursor = &nav_string[0..self.input_pointer as usize];
+        if nav_end - self.nav_scroll < self.timeline.len() {
+            let position = format!(
+                "presses {}-{} of {}",
+                self.nav_scroll + 1,
+                nav_end,
+                self.timeline.len()
+            );
+            frame.render_widget(
+                Paragraph::new(position).style(label_style).right_aligned(),
+                row_rect(level_count as u16 + 1),
+            );
         }
-        let cursor = &nav_string[self.input_pointer as usize..self.input_pointer as usize + 1];
-        let post_cursor = &nav_string[self.input_pointer as usize + 1..];
 
-        // define areas
+        // render fault report
 
-        let pre_rect: Rect = Rect {
-            x: nav_area.x + 1,
-            y: nav_area.y + 1,
-            width: pre_cursor.len() as u16,
-            height: 1,
-        };
-        let cursor_rect: Rect = Rect {
-            x: pre_rect.x + pre_rect.width,
-            y: nav_area.y + 1,
-            width: 1,
-            height: 1,
-        };
-        let post_rect: Rect = Rect {
-            x: cursor_rect.x + 1,
-            y: cursor_rect.y,
-            width: nav_area.width - pre_rect.width - 3,
-            height: 1,
-        };
-
-        let pre_para = Paragraph::new(pre_cursor).style(Style::new().white().on_blue());
-        let cursor_para = Paragraph::new(cursor).style(Style::new().black().on_green());
-        let post_para = Paragraph::new(post_cursor).style(Style::new().white().on_blue());
-        frame.render_widget(pre_para, pre_rect);
-        frame.render_widget(cursor_para, cursor_rect);
-        frame.render_widget(post_para, post_rect);
+        if let Some(fault) = &self.line_fault {
+            let lines = if state.fault.is_some() {
+                fault_report(fault, pad_count)
+            } else {
+                vec![format!(
+                    "{} panics at step {}, press f to jump there",
+                    pad_name(fault.pad, pad_count),
+                    fault.step + 1
+                )]
+            };
+            let block = Block::bordered()
+                .title(" fault ")
+                .style(box_error_style)
+                .padding(Padding::horizontal(1));
+            frame.render_widget(Paragraph::new(lines.join("\n")).block(block), fault_area);
+        }
 
         // render help popup, make sure to render last
 
+        let input_line = format!("Input is read from {}", self.input_path.display());
         let help_lines = [
             "",
             "This tool simulates the keypads for Advent of Code 2024 Day 21.",
             "",
-            "Input is read from file keypad2024input.txt",
+            input_line.as_str(),
+            "Pad layouts are read with --layout FILE, see keypad2024layout.txt",
+            "keysim --help lists all command line options",
             "",
             "Keys;",
-            "1 - 9 select input strings",
+            "up and down select input strings, 1 - 9 pick one of the first nine",
             "left and right arrows navigate through selected string",
+            "Home and End jump to start and end, PageUp and PageDown move by the stride",
+            "< and > halve or double the stride",
+            "e and E jump to the next or previous output of the num pad",
+            "k selects a pad, ] and [ jump to the next or previous press on it",
+            "rows under the string show what each pad pressed, under the press that caused it",
+            "l hides or shows the list of strings, p the rows under the string",
+            "colors mark the presses that make each pad press once, blue around the cursor",
+            "space starts or pauses autoplay, + and - change its speed",
+            "d and D remove or add a robot pad, , and . scroll the pads if not all fit",
+            "s solves a numeric code and adds the sequence to the input strings",
+            "codes like 029A in the input file are solved at the current depth",
+            "lines with => show whether they give the expected result",
+            "c shows length and complexity of the numeric codes in the input file",
+            "r lists the lines of the input file that were rejected",
+            "i edits the selected string: type U D L R A or arrows, Backspace and Delete remove",
+            "ctrl-z and ctrl-y undo and redo, ctrl-s saves to the input file, ctrl-n as a new line",
+            "m records a new string, arrows and Enter or space press the manual pad",
+            "g starts a game: type a code with as few presses as possible, scores go to keysim-scores.txt",
+            "every press of a game counts, also the ones taken back with Backspace",
+            "h highlights the best next press towards the code of the game or the string, a replaces the presses after the cursor with the rest",
+            "x shows which presses caused the last press on the pad selected with k",
+            "t writes the event trace of the selected string to keysim-trace-N.jsonl",
+            "f jumps to the press that makes a robot panic",
             "? shows this help, q quits the program",
             "",
         ];
@@ -533,29 +1991,302 @@ impl App {
             .title(Line::from(" Help ").centered());
         if self.show_help {
             frame.render_widget(Clear, popup_rect);
-            frame.render_widget(Paragraph::new(help_text).block(block), popup_rect);
+            frame.render_widget(
+                Paragraph::new(help_text)
+                    .wrap(Wrap { trim: true })
+                    .block(block),
+                popup_rect,
+            );
+        }
+
+        // render info popup
+
+        if let Some(info) = &self.info {
+            let mut lines: Vec<Line> = info.lines.iter().map(|l| Line::raw(l.as_str())).collect();
+            lines.push(Line::raw(""));
+            lines.push(Line::raw("ESC to close").centered().black().on_green());
+            let block = Block::bordered()
+                .title(Line::from(info.title.as_str()).centered())
+                .padding(Padding::new(2, 2, 1, 0));
+            frame.render_widget(Clear, popup_rect);
+            frame.render_widget(Paragraph::new(lines).block(block), popup_rect);
+        }
+
+        // render expansion tree, scrolled to keep the selected node visible
+
+        if let Some(view) = &self.tree {
+            let rows = view.rows();
+            let height = popup_rect.height.saturating_sub(5) as usize;
+            let skip = (view.selected + 1).saturating_sub(height);
+            let mut lines: Vec<Line> = rows
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .take(height)
+                .map(|(row, (id, depth))| {
+                    let node = &view.tree.nodes[*id];
+                    let marker = match (node.children.is_empty(), view.folded.contains(id)) {
+                        (true, _) => ' ',
+                        (false, true) => '+',
+                        (false, false) => '-',
+                    };
+                    let label = if node.pad < pad_count - 1 {
+                        display_label(node.label)
+                    } else {
+                        node.label
+                    };
+                    let range = if node.first_step == node.step {
+                        format!("press {}", node.step + 1)
+                    } else {
+                        format!("presses {}-{}", node.first_step + 1, node.step + 1)
+                    };
+                    let text = format!(
+                        "{:indent$}{marker} {:<4}{label}   {range}",
+                        "",
+                        short_pad_name(node.pad, pad_count),
+                        indent = depth * 2
+                    );
+                    if row == view.selected {
+                        Line::raw(text).black().on_green()
+                    } else {
+                        Line::raw(text)
+                    }
+                })
+                .collect();
+            lines.push(Line::raw(""));
+            lines.push(
+                Line::raw(
+                    "up/down select, left/right fold, Enter jump there, d writes dot, ESC close",
+                )
+                .centered()
+                .black()
+                .on_green(),
+            );
+            let block = Block::bordered()
+                .title(Line::from(" expansion tree ").centered())
+                .padding(Padding::new(2, 2, 1, 0));
+            frame.render_widget(Clear, popup_rect);
+            frame.render_widget(Paragraph::new(lines).block(block), popup_rect);
+        }
+
+        // render prompt
+
+        if let Some(prompt) = &self.prompt {
+            let title = match prompt.kind {
+                PromptKind::Solve => " code to solve, e.g. 029A ",
+                PromptKind::Game => " code to type, empty for a random one ",
+                PromptKind::Hint | PromptKind::Autopilot => " code to type, e.g. 029A ",
+            };
+            let prompt_rect = centered_rect(&frame.area(), 40, 3);
+            let block = Block::bordered().title(Line::from(title).centered());
+            frame.render_widget(Clear, prompt_rect);
+            frame.render_widget(
+                Paragraph::new(format!("{}_", prompt.text)).block(block),
+                prompt_rect,
+            );
         }
     }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+    let args = cli::parse(std::env::args().skip(1))?;
+    if args.command == Command::Help {
+        print!("{HELP}");
+        return Ok(());
+    }
+    let layout = match &args.layout {
+        Some(path) => ChainLayout::load(path)?,
+        None => ChainLayout::default(),
+    };
+    if args.command != Command::Tui {
+        let input = input::load(&args.input, &layout.numeric)?;
+        for r in &input.rejected {
+            eprintln!("{}:{}: skipped, {}", args.input.display(), r.line, r.reason);
+        }
+        return match args.command {
+            Command::Solve => commands::solve(&args, &layout, &input),
+            Command::Complexity => commands::complexity(&args, &layout, &input),
+            Command::Trace => commands::trace(&args, &layout, &input),
+            Command::Simulate => {
+                if !commands::simulate(&args, &layout, &input)? {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            Command::Check => {
+                if !commands::check(&args, &layout, &input)? {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+            Command::Tui | Command::Help => unreachable!("handled above"),
+        };
+    }
+    let input = input::load(&args.input, &layout.numeric).and_then(|input| {
+        if input.sequence_count() == 0 {
+            bail!("no sequences or codes in {}", args.input.display());
+        }
+        let line = args.line.unwrap_or(0);
+        if line >= input.sequence_count() {
+            bail!(
+                "{} has {} sequences, cannot select sequence {}",
+                args.input.display(),
+                input.sequence_count(),
+                line + 1
+            );
+        }
+        Ok(input)
+    });
     let terminal = ratatui::init();
-    let app_result = App::new().run(terminal);
+    let app_result = match input {
+        Ok(input) => App::new(layout, input, &args).run(terminal),
+        Err(e) => error_screen(terminal, &e),
+    };
     ratatui::restore();
     app_result
 }
 
-/// Calculate the layout of the UI elements.
-///
-/// Returns a tuple of the title area and the main areas.
+/// Show why keysim cannot start until a key is pressed.
+fn error_screen(mut terminal: DefaultTerminal, error: &Report) -> Result<()> {
+    let mut lines = vec![Line::raw("keysim cannot start:").bold(), Line::raw("")];
+    lines.extend(error.chain().map(|e| Line::raw(e.to_string())));
+    lines.push(Line::raw(""));
+    lines.push(Line::raw("keysim --help shows how to select an input file"));
+    lines.push(Line::raw(""));
+    lines.push(
+        Line::raw("press any key to quit")
+            .centered()
+            .black()
+            .on_green(),
+    );
+    loop {
+        terminal.draw(|frame| {
+            let width = frame.area().width.saturating_sub(4).min(70);
+            let area = centered_rect(&frame.area(), width, lines.len() as u16 + 4);
+            let block = Block::bordered()
+                .title(Line::from(" error ").centered())
+                .style(Style::new().white().on_red())
+                .padding(Padding::new(2, 2, 1, 0));
+            frame.render_widget(
+                Paragraph::new(lines.clone())
+                    .wrap(Wrap { trim: false })
+                    .block(block),
+                area,
+            );
+        })?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Where and why a robot panicked, and which presses led there.
+fn fault_report(fault: &Fault, pad_count: usize) -> Vec<String> {
+    let name = pad_name(fault.pad, pad_count);
+    let (x, y) = fault.to;
+    let what = match fault.kind {
+        FaultKind::InvalidInput(c) => format!("{name} got {c}, which is not a button"),
+        FaultKind::OffGrid => format!(
+            "{name} moved {} from {:?} off the edge of the pad to ({x}, {y})",
+            display_label(fault.signal),
+            fault.from
+        ),
+        FaultKind::Gap => format!(
+            "{name} moved {} from {:?} into the gap at ({x}, {y})",
+            display_label(fault.signal),
+            fault.from
+        ),
+    };
+    let presses = fault
+        .presses
+        .iter()
+        .enumerate()
+        .map(|(pad, c)| format!("{} on {}", display_label(*c), pad_name(pad, pad_count)))
+        .collect::<Vec<String>>()
+        .join(" -> ");
+    vec![
+        format!("step {}: {what}", fault.step + 1),
+        format!("presses: {presses}"),
+    ]
+}
+
+/// Box title for pad number `pad` in a chain of `pad_count` pads.
+fn pad_name(pad: usize, pad_count: usize) -> String {
+    match pad {
+        0 => "manual pad".to_string(),
+        p if p == pad_count - 1 => "num pad".to_string(),
+        p => format!("robot pad {p}"),
+    }
+}
+
+/// Width and height of the box of a pad with `width` x `height` buttons.
+fn pad_size(width: i32, height: i32, compact: bool) -> (u16, u16) {
+    let (width, height) = (width as u16, height as u16);
+    if compact {
+        (width * 3 + 4, height + 2)
+    } else {
+        (width * 7 + 4, height * 3 + 2)
+    }
+}
+
+/// Split `pads` into rows no wider than `width`, filling every row before
+/// starting the next, with room for three columns between the boxes.
+fn wrap_pads(sizes: &[(u16, u16)], pads: Range<usize>, width: u16) -> Vec<Range<usize>> {
+    let mut rows = vec![];
+    let (mut start, mut used) = (pads.start, 0);
+    for pad in pads.clone() {
+        if pad > start && used + 3 + sizes[pad].0 > width {
+            rows.push(start..pad);
+            (start, used) = (pad, 0);
+        }
+        used += if pad > start { 3 } else { 0 } + sizes[pad].0;
+    }
+    if start < pads.end {
+        rows.push(start..pads.end);
+    }
+    rows
+}
+
+/// Height of `rows` of pads, one above the other.
+fn pads_height(sizes: &[(u16, u16)], rows: &[Range<usize>]) -> u16 {
+    let boxes: u16 = rows
+        .iter()
+        .map(|row| sizes[row.clone()].iter().map(|s| s.1).max().unwrap_or(0))
+        .sum();
+    boxes + rows.len().saturating_sub(1) as u16
+}
 
+/// Pad name short enough for the row labels under the nav bar.
+fn short_pad_name(pad: usize, pad_count: usize) -> String {
+    match pad {
+        0 => "man".to_string(),
+        p if p == pad_count - 1 => "num".to_string(),
+        p => format!("r{p}"),
+    }
+}
+
+/// Rect of at most `width` x `height` in the middle of `r`.
+fn centered_rect(r: &Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(r.width);
+    let height = height.min(r.height);
+    Rect {
+        x: r.x + (r.width - width) / 2,
+        y: r.y + (r.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Shrink `r` by the width of a border on every side.
 fn inner_rect(r: &Rect) -> Rect {
     Rect {
         x: r.x + 1,
         y: r.y + 1,
-        width: r.width - 2,
-        height: r.height - 2,
+        width: r.width.saturating_sub(2),
+        height: r.height.saturating_sub(2),
     }
 }
 
@@ -597,27 +2328,3 @@ fn render_button(
         .padding(Padding::new(0, 0, 0, 0));
     frame.render_widget(paragraph.clone().block(block), area);
 }
-
-fn load_file() -> Vec<String> {
-    let filename = "keypad2024input.txt";
-    let mut lines = Vec::new();
-    //println!("{:?}", path);
-    for line in read_to_string(filename).unwrap().lines() {
-        let mut l = line.to_string();
-        if l.len() == 0 {
-            continue;
-        }
-        if l.chars().all(|c| "<>^vA".contains(c)) {
-            l = l.replacen("v", "D", 99999);
-            l = l.replacen("^", "U", 99999);
-            l = l.replacen("<", "L", 99999);
-            l = l.replacen(">", "R", 99999);
-        }
-        if l.chars().all(|c| "LRUDA".contains(c)) {
-            if lines.len() < 9 {
-                lines.push(l);
-            }
-        }
-    }
-    lines
-}
@@ -0,0 +1,79 @@
+//! High scores of the game mode, kept in a text file.
+//!
+//! Every line holds one finished game: the code, the depth, the manual
+//! presses used and the fewest presses that type the code, separated by
+//! spaces. Lines that cannot be read are skipped.
+
+use color_eyre::eyre::{Result, WrapErr};
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+/// Score file used by the TUI, in the working directory.
+pub const SCORE_FILE: &str = "keysim-scores.txt";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Score {
+    pub code: String,
+    pub depth: usize,
+    pub presses: usize,
+    /// Length of a shortest sequence typing the code.
+    pub optimal: u128,
+}
+
+impl Score {
+    /// Points out of 1000, all of them for a shortest sequence.
+    pub fn points(&self) -> u128 {
+        self.optimal * 1000 / self.presses.max(1) as u128
+    }
+}
+
+/// Scores in `path`, none if the file does not exist yet.
+pub fn load(path: &Path) -> Result<Vec<Score>> {
+    let text = match read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => {
+            return Err(e).wrap_err_with(|| format!("cannot read {}", path.display()));
+        }
+    };
+    Ok(text.lines().filter_map(parse_line).collect())
+}
+
+/// Add `score` at the end of `path`.
+pub fn append(path: &Path, score: &Score) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err_with(|| format!("cannot open {}", path.display()))?;
+    writeln!(
+        file,
+        "{} {} {} {}",
+        score.code, score.depth, score.presses, score.optimal
+    )
+    .wrap_err_with(|| format!("cannot write {}", path.display()))
+}
+
+/// The `count` best scores for `code` at `depth`, best first, earlier games
+/// first among equal ones.
+pub fn best<'a>(scores: &'a [Score], code: &str, depth: usize, count: usize) -> Vec<&'a Score> {
+    let mut best: Vec<&Score> = scores
+        .iter()
+        .filter(|s| s.code == code && s.depth == depth)
+        .collect();
+    best.sort_by_key(|s| s.presses);
+    best.truncate(count);
+    best
+}
+
+fn parse_line(line: &str) -> Option<Score> {
+    let mut fields = line.split_whitespace();
+    let score = Score {
+        code: fields.next()?.to_string(),
+        depth: fields.next()?.parse().ok()?,
+        presses: fields.next()?.parse().ok()?,
+        optimal: fields.next()?.parse().ok()?,
+    };
+    fields.next().is_none().then_some(score)
+}
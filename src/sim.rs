@@ -0,0 +1,296 @@
+//! Keypad chain simulation for Advent of Code 2024 day 21.
+//!
+//! A [`Simulator`] owns the state of the manual pad, the robot operated
+//! directional pads and the numeric pad. Every manual press is fed in with
+//! [`Simulator::step`] and travels down the chain until a robot moves its arm,
+//! the numeric pad emits a key, or a robot panics.
+
+use crate::keypad::{moved, ChainLayout, Keypad};
+use crate::trace::Event;
+
+/// Button coordinate on a pad, `(x, y)` with `(0, 0)` in the top left corner.
+pub type Pos = (i32, i32);
+
+/// Number of robot operated directional pads between the manual pad and the
+/// num pad in part one of the puzzle.
+pub const DEFAULT_DEPTH: usize = 2;
+
+/// What happened to a single pad during the last step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PadState {
+    pub pos: Pos,
+    pub has_error: bool,
+    pub just_moved_from: Option<Pos>,
+    pub just_pressed: bool,
+    /// Number of buttons pressed on this pad so far.
+    pub presses: usize,
+}
+
+/// Why a robot panicked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The manual pad has no button for this character.
+    InvalidInput(char),
+    /// The arm was moved past the edge of the pad.
+    OffGrid,
+    /// The arm was moved onto the gap of the pad.
+    Gap,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    /// Zero based index of the manual press that caused the fault.
+    pub step: usize,
+    pub pad: usize,
+    pub kind: FaultKind,
+    /// Signal that reached `pad`.
+    pub signal: char,
+    /// Arm position before the failed move.
+    pub from: Pos,
+    /// Position the arm was sent to, equal to `from` for invalid input.
+    pub to: Pos,
+    /// Buttons pressed on the pads before `pad` during this step, starting
+    /// with the manual press.
+    pub presses: Vec<char>,
+}
+
+/// Outcome of feeding one manual press into the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepResult {
+    /// The robot at `pad` moved its arm, which absorbed the press.
+    Moved { pad: usize },
+    /// The num pad robot pressed a button.
+    Emitted(char),
+    /// A robot panicked, the chain stops accepting presses.
+    Fault(Fault),
+    /// The chain faulted before, the press was ignored.
+    Halted,
+}
+
+/// Snapshot of the whole chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainState {
+    pub pads: Vec<PadState>,
+    pub output: String,
+    pub steps: usize,
+    pub fault: Option<Fault>,
+}
+
+pub struct Simulator {
+    pads: Vec<Keypad>,
+    state: ChainState,
+    events: Vec<Event>,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH)
+    }
+}
+
+impl Simulator {
+    /// Build a chain with `depth` directional robot pads between the manual
+    /// pad and the num pad.
+    pub fn new(depth: usize) -> Self {
+        Self::with_layout(&ChainLayout::default(), depth)
+    }
+
+    /// Build a chain from the pad designs in `layout`.
+    pub fn with_layout(layout: &ChainLayout, depth: usize) -> Self {
+        let pads = layout.pads(depth);
+        let state = Self::initial_state(&pads);
+        Self {
+            pads,
+            state,
+            events: vec![],
+        }
+    }
+
+    /// Number of directional robot pads in the chain.
+    pub fn depth(&self) -> usize {
+        self.pads.len() - 2
+    }
+
+    /// Number of pads including the manual pad and the num pad.
+    pub fn pad_count(&self) -> usize {
+        self.pads.len()
+    }
+
+    pub fn keypad(&self, pad: usize) -> &Keypad {
+        &self.pads[pad]
+    }
+
+    fn initial_state(pads: &[Keypad]) -> ChainState {
+        ChainState {
+            pads: pads
+                .iter()
+                .map(|p| PadState {
+                    pos: p.start(),
+                    ..PadState::default()
+                })
+                .collect(),
+            output: String::new(),
+            steps: 0,
+            fault: None,
+        }
+    }
+
+    /// Put every arm back on its start button and forget the output.
+    pub fn reset(&mut self) {
+        self.state = Self::initial_state(&self.pads);
+        self.events.clear();
+    }
+
+    pub fn state(&self) -> &ChainState {
+        &self.state
+    }
+
+    pub fn snapshot(&self) -> ChainState {
+        self.state.clone()
+    }
+
+    /// Continue from a state taken with [`Simulator::snapshot`].
+    pub fn restore(&mut self, state: ChainState) {
+        self.state = state;
+        self.events.clear();
+    }
+
+    /// Feed every character of `input` into the chain.
+    pub fn run(&mut self, input: &str) -> &ChainState {
+        for c in input.chars() {
+            self.step(c);
+        }
+        &self.state
+    }
+
+    /// Events of the last step.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Reset the chain, run all of `input` and collect the events of every
+    /// step.
+    pub fn trace(&mut self, input: &str) -> Vec<Event> {
+        self.reset();
+        let mut events = vec![];
+        for c in input.chars() {
+            if self.step(c) == StepResult::Halted {
+                break;
+            }
+            events.extend_from_slice(&self.events);
+        }
+        events
+    }
+
+    /// Press `input` on the manual pad and propagate the signal down the chain.
+    pub fn step(&mut self, input: char) -> StepResult {
+        self.events.clear();
+        if self.state.fault.is_some() {
+            return StepResult::Halted;
+        }
+        let step = self.state.steps;
+        self.state.steps += 1;
+        for p in self.state.pads.iter_mut() {
+            p.just_moved_from = None;
+            p.just_pressed = false;
+        }
+
+        match self.pads[0].position(input) {
+            Some(pos) => {
+                self.state.pads[0].pos = pos;
+                self.state.pads[0].just_pressed = true;
+                self.state.pads[0].presses += 1;
+                self.events.push(Event::Pressed {
+                    step,
+                    pad: 0,
+                    label: input,
+                });
+            }
+            None => {
+                let pos = self.state.pads[0].pos;
+                return self.fault(step, 0, FaultKind::InvalidInput(input), input, pos);
+            }
+        }
+
+        let mut signal = input;
+        for pad in 1..self.pads.len() {
+            let last_pos = self.state.pads[pad].pos;
+            if signal == 'A' {
+                self.state.pads[pad].just_pressed = true;
+                self.state.pads[pad].presses += 1;
+                signal = self.pads[pad]
+                    .label(last_pos)
+                    .expect("arm rests on a button");
+                self.events.push(Event::Pressed {
+                    step,
+                    pad,
+                    label: signal,
+                });
+                continue;
+            }
+            let Some(next) = moved(last_pos, signal) else {
+                return self.fault(step, pad, FaultKind::InvalidInput(signal), signal, last_pos);
+            };
+            if !self.pads[pad].on_grid(next) {
+                return self.fault(step, pad, FaultKind::OffGrid, signal, next);
+            }
+            if self.pads[pad].label(next).is_none() {
+                return self.fault(step, pad, FaultKind::Gap, signal, next);
+            }
+            self.state.pads[pad].pos = next;
+            self.state.pads[pad].just_moved_from = Some(last_pos);
+            self.events.push(Event::Moved {
+                step,
+                pad,
+                from: last_pos,
+                to: next,
+            });
+            return StepResult::Moved { pad };
+        }
+
+        self.state.output.push(signal);
+        self.events.push(Event::Emitted {
+            step,
+            label: signal,
+        });
+        StepResult::Emitted(signal)
+    }
+
+    fn fault(
+        &mut self,
+        step: usize,
+        pad: usize,
+        kind: FaultKind,
+        signal: char,
+        to: Pos,
+    ) -> StepResult {
+        let from = self.state.pads[pad].pos;
+        let presses = self
+            .events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Pressed { label, .. } => Some(*label),
+                _ => None,
+            })
+            .collect();
+        let fault = Fault {
+            step,
+            pad,
+            kind,
+            signal,
+            from,
+            to,
+            presses,
+        };
+        self.state.pads[pad].has_error = true;
+        self.state.fault = Some(fault.clone());
+        self.events.push(Event::Fault {
+            step,
+            pad,
+            kind,
+            from,
+            to,
+        });
+        StepResult::Fault(fault)
+    }
+}
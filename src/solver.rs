@@ -0,0 +1,461 @@
+//! Shortest manual sequences for a numeric code.
+//!
+//! Pressing a button on pad `k` only depends on where the arm of pad `k` is,
+//! because every pad closer to the manual pad rests on `A` after each press
+//! it passes on. So the cost of going from button `a` to button `b` and
+//! pressing it on pad `k` is a shortest path over (arm position, last button
+//! pressed on pad `k - 1`), with edge weights taken from pad `k - 1`. The
+//! search is not limited to straight paths, so layouts with several gaps work
+//! as well.
+//!
+//! The same holds from any other chain state once the num pad pressed its
+//! first button. Until then only the first press of every pad starts from
+//! where its arm happens to be, see [`Solver::solve_from`].
+
+use color_eyre::eyre::{bail, eyre, Result};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::keypad::{moved, ChainLayout, Keypad, DIR_LABELS};
+use crate::sim::Pos;
+
+/// Longest sequence [`Solver::solve`] is willing to build.
+pub const MAX_SEQUENCE_LEN: u128 = 10_000_000;
+
+/// Cheapest way to press one button on one pad.
+#[derive(Clone, Debug)]
+struct Move {
+    /// Number of manual presses, `u128::MAX` if it does not fit.
+    cost: u128,
+    /// Buttons pressed on the pad below, ending with `A`.
+    presses: Vec<char>,
+}
+
+pub struct Solver {
+    pads: Vec<Keypad>,
+    moves: HashMap<(usize, char, char), Move>,
+}
+
+impl Solver {
+    /// Solver for a chain with `depth` directional robot pads, built from the
+    /// same pads as [`crate::sim::Simulator::with_layout`].
+    pub fn new(layout: &ChainLayout, depth: usize) -> Self {
+        Self {
+            pads: layout.pads(depth),
+            moves: HashMap::new(),
+        }
+    }
+
+    /// Number of manual presses of the shortest sequence typing `code`.
+    pub fn length(&mut self, code: &str) -> Result<u128> {
+        let top = self.pads.len() - 1;
+        self.check_code(code)?;
+        let mut from = 'A';
+        let mut total: u128 = 0;
+        for to in code.chars() {
+            total = total.saturating_add(self.cost(top, from, to));
+            from = to;
+        }
+        fits(total, code)
+    }
+
+    /// Length of the shortest sequence times the numeric part of `code`, the
+    /// complexity of the puzzle.
+    pub fn complexity(&mut self, code: &str) -> Result<u128> {
+        self.length(code)?
+            .checked_mul(numeric_part(code))
+            .ok_or_else(|| eyre!("complexity of {code} does not fit in u128"))
+    }
+
+    /// Sum of the complexities of all `codes`.
+    pub fn total_complexity<S: AsRef<str>>(&mut self, codes: &[S]) -> Result<u128> {
+        let mut total: u128 = 0;
+        for code in codes {
+            total = total
+                .checked_add(self.complexity(code.as_ref())?)
+                .ok_or_else(|| eyre!("sum of the complexities does not fit in u128"))?;
+        }
+        Ok(total)
+    }
+
+    /// A shortest manual sequence typing `code`, in `U D L R A` form.
+    pub fn solve(&mut self, code: &str) -> Result<String> {
+        let length = self.length(code)?;
+        if length > MAX_SEQUENCE_LEN {
+            bail!("sequence for {code} has {length} presses, too long to build");
+        }
+        let top = self.pads.len() - 1;
+        let mut sequence = String::with_capacity(length as usize);
+        let mut from = 'A';
+        for to in code.chars() {
+            self.expand(top, from, to, &mut sequence);
+            from = to;
+        }
+        Ok(sequence)
+    }
+
+    /// Number of manual presses of the shortest sequence typing `code` when
+    /// the arm of pad `k` rests on `arms[k]` instead of on `A`, manual pad
+    /// first.
+    pub fn length_from(&mut self, arms: &[char], code: &str) -> Result<u128> {
+        let top = self.pads.len() - 1;
+        self.check_code(code)?;
+        self.check_arms(arms)?;
+        let mut chars = code.chars();
+        let Some(first) = chars.next() else {
+            return Ok(0);
+        };
+        let mut total = self.first_move(arms, top, first, &mut HashMap::new()).cost;
+        let mut from = first;
+        for to in chars {
+            total = total.saturating_add(self.cost(top, from, to));
+            from = to;
+        }
+        fits(total, code)
+    }
+
+    /// A shortest manual sequence typing `code` from the arms in `arms`, see
+    /// [`Solver::length_from`].
+    pub fn solve_from(&mut self, arms: &[char], code: &str) -> Result<String> {
+        let length = self.length_from(arms, code)?;
+        if length > MAX_SEQUENCE_LEN {
+            bail!("sequence for {code} has {length} presses, too long to build");
+        }
+        let top = self.pads.len() - 1;
+        let mut sequence = String::with_capacity(length as usize);
+        let mut chars = code.chars();
+        let Some(first) = chars.next() else {
+            return Ok(sequence);
+        };
+        let mut memo = HashMap::new();
+        self.expand_first(arms, top, first, &mut memo, &mut sequence);
+        let mut from = first;
+        for to in chars {
+            self.expand(top, from, to, &mut sequence);
+            from = to;
+        }
+        Ok(sequence)
+    }
+
+    /// The first manual press of [`Solver::solve_from`], without building
+    /// the rest of the sequence. `None` if `code` is empty.
+    pub fn first_press(&mut self, arms: &[char], code: &str) -> Result<Option<char>> {
+        self.check_code(code)?;
+        self.check_arms(arms)?;
+        let Some(mut to) = code.chars().next() else {
+            return Ok(None);
+        };
+        let mut memo = HashMap::new();
+        for pad in (1..self.pads.len()).rev() {
+            to = self.first_move(arms, pad, to, &mut memo).presses[0];
+        }
+        Ok(Some(to))
+    }
+
+    fn check_code(&self, code: &str) -> Result<()> {
+        let numeric = &self.pads[self.pads.len() - 1];
+        match code.chars().find(|c| numeric.position(*c).is_none()) {
+            Some(c) => Err(eyre!("the num pad has no button {c}")),
+            None => Ok(()),
+        }
+    }
+
+    fn check_arms(&self, arms: &[char]) -> Result<()> {
+        if arms.len() != self.pads.len() {
+            bail!("{} arms given for {} pads", arms.len(), self.pads.len());
+        }
+        match arms
+            .iter()
+            .enumerate()
+            .find(|(pad, c)| self.pads[*pad].position(**c).is_none())
+        {
+            Some((pad, c)) => Err(eyre!("pad {pad} has no button {c}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Append the manual presses that make pad `pad` press `to` while the
+    /// arms rest on `arms`, see [`Solver::first_move`].
+    fn expand_first(
+        &mut self,
+        arms: &[char],
+        pad: usize,
+        to: char,
+        memo: &mut HashMap<(usize, char), Move>,
+        sequence: &mut String,
+    ) {
+        if pad == 0 {
+            sequence.push(to);
+            return;
+        }
+        let presses = self.first_move(arms, pad, to, memo).presses;
+        self.expand_first(arms, pad - 1, presses[0], memo, sequence);
+        for pair in presses.windows(2) {
+            self.expand(pad - 1, pair[0], pair[1], sequence);
+        }
+    }
+
+    /// Append the manual presses that move pad `pad` from `from` to `to` and
+    /// press it.
+    fn expand(&mut self, pad: usize, from: char, to: char, sequence: &mut String) {
+        if pad == 0 {
+            sequence.push(to);
+            return;
+        }
+        let presses = self.best_move(pad, from, to).presses;
+        let mut below = 'A';
+        for press in presses {
+            self.expand(pad - 1, below, press, sequence);
+            below = press;
+        }
+    }
+
+    fn cost(&mut self, pad: usize, from: char, to: char) -> u128 {
+        if pad == 0 {
+            return 1;
+        }
+        self.best_move(pad, from, to).cost
+    }
+
+    fn best_move(&mut self, pad: usize, from: char, to: char) -> Move {
+        if let Some(m) = self.moves.get(&(pad, from, to)) {
+            return m.clone();
+        }
+        let first = DIR_LABELS.map(|c| (self.cost(pad - 1, 'A', c), c));
+        let m = self.search(pad, from, &first, to);
+        self.moves.insert((pad, from, to), m.clone());
+        m
+    }
+
+    /// Cheapest way to make pad `pad` press `to` while the arm of every pad
+    /// up to `pad` rests on `arms[k]`. The pads below rest on `A` after the
+    /// first press they pass on, so only that one differs from
+    /// [`Solver::best_move`]. `memo` holds the moves found for these `arms`.
+    fn first_move(
+        &mut self,
+        arms: &[char],
+        pad: usize,
+        to: char,
+        memo: &mut HashMap<(usize, char), Move>,
+    ) -> Move {
+        if pad == 0 {
+            return Move {
+                cost: 1,
+                presses: vec![],
+            };
+        }
+        if let Some(m) = memo.get(&(pad, to)) {
+            return m.clone();
+        }
+        let first = DIR_LABELS.map(|c| (self.first_move(arms, pad - 1, c, memo).cost, c));
+        let m = self.search(pad, arms[pad], &first, to);
+        memo.insert((pad, to), m.clone());
+        m
+    }
+
+    /// Dijkstra over (arm position on `pad`, last button pressed on the pad
+    /// below, with the pads below that resting on `A`) from `from` until
+    /// `to` is pressed. `first` holds the cost of every button as first
+    /// press on the pad below.
+    fn search(&mut self, pad: usize, from: char, first: &[(u128, char)], to: char) -> Move {
+        let keypad = self.pads[pad].clone();
+        let start = keypad.position(from).expect("from is a button");
+        let target = keypad.position(to).expect("to is a button");
+
+        let mut dist: HashMap<(Pos, char), u128> = HashMap::new();
+        let mut prev: HashMap<(Pos, char), (Pos, char)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        // `None` stands for pressing `to` right away
+        let mut best: Option<(u128, Option<(Pos, char)>)> = None;
+        for &(d, c) in first {
+            if c == 'A' {
+                if start == target && best.is_none_or(|(b, _)| d < b) {
+                    best = Some((d, None));
+                }
+                continue;
+            }
+            let next = moved(start, c).expect("c is a direction");
+            if keypad.label(next).is_some() && dist.get(&(next, c)).is_none_or(|old| d < *old) {
+                dist.insert((next, c), d);
+                heap.push(Reverse((d, next, c)));
+            }
+        }
+
+        while let Some(Reverse((d, pos, below))) = heap.pop() {
+            if dist.get(&(pos, below)).is_some_and(|best| d > *best) {
+                continue;
+            }
+            if best.is_some_and(|(b, _)| d >= b) {
+                break;
+            }
+            if pos == target {
+                let total = d.saturating_add(self.cost(pad - 1, below, 'A'));
+                if best.is_none_or(|(b, _)| total < b) {
+                    best = Some((total, Some((pos, below))));
+                }
+            }
+            for dir in DIR_LABELS.into_iter().filter(|c| *c != 'A') {
+                let next = moved(pos, dir).expect("dir is a direction");
+                if keypad.label(next).is_none() {
+                    continue;
+                }
+                let nd = d.saturating_add(self.cost(pad - 1, below, dir));
+                if dist.get(&(next, dir)).is_none_or(|old| nd < *old) {
+                    dist.insert((next, dir), nd);
+                    prev.insert((next, dir), (pos, below));
+                    heap.push(Reverse((nd, next, dir)));
+                }
+            }
+        }
+
+        let (cost, mut state) = best.expect("target is reachable");
+        let mut presses = vec!['A'];
+        while let Some(s) = state {
+            presses.push(s.1);
+            state = prev.get(&s).copied();
+        }
+        presses.reverse();
+        Move { cost, presses }
+    }
+}
+
+/// `length` of the sequence for `code`, an error if it saturated.
+fn fits(length: u128, code: &str) -> Result<u128> {
+    if length == u128::MAX {
+        bail!("length of the sequence for {code} does not fit in u128");
+    }
+    Ok(length)
+}
+
+/// The number formed by the digits of `code`, `029A` gives 29.
+pub fn numeric_part(code: &str) -> u128 {
+    code.chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |n, d| n.saturating_mul(10).saturating_add(d as u128))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{ChainState, Simulator};
+    use std::collections::{HashSet, VecDeque};
+
+    const EXAMPLE: [(&str, u128); 5] = [
+        ("029A", 68),
+        ("980A", 60),
+        ("179A", 68),
+        ("456A", 64),
+        ("379A", 64),
+    ];
+
+    fn codes() -> Vec<&'static str> {
+        EXAMPLE.iter().map(|(code, _)| *code).collect()
+    }
+
+    #[test]
+    fn example_lengths_at_depth_2() {
+        let mut solver = Solver::new(&ChainLayout::default(), 2);
+        for (code, length) in EXAMPLE {
+            assert_eq!(solver.length(code).unwrap(), length, "{code}");
+            let sequence = solver.solve(code).unwrap();
+            assert_eq!(sequence.len() as u128, length, "{code}");
+            let mut sim = Simulator::new(2);
+            assert_eq!(sim.run(&sequence).output, code);
+        }
+        assert_eq!(solver.total_complexity(&codes()).unwrap(), 126384);
+    }
+
+    #[test]
+    fn example_total_at_depth_25() {
+        let mut solver = Solver::new(&ChainLayout::default(), 25);
+        assert_eq!(solver.total_complexity(&codes()).unwrap(), 154115708116294);
+    }
+
+    #[test]
+    fn too_deep_is_an_error() {
+        let mut solver = Solver::new(&ChainLayout::default(), 100);
+        assert!(solver.length("029A").is_err());
+    }
+
+    /// Fewest presses typing `code` from `start`, by breadth first search
+    /// over the chain states.
+    fn bfs(sim: &mut Simulator, start: ChainState, code: &str) -> Option<u128> {
+        let key = |s: &ChainState| {
+            let arms: Vec<Pos> = s.pads.iter().map(|p| p.pos).collect();
+            (arms, s.output.clone())
+        };
+        let mut seen = HashSet::from([key(&start)]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((state, presses)) = queue.pop_front() {
+            if state.output == code {
+                return Some(presses);
+            }
+            for c in DIR_LABELS {
+                sim.restore(state.clone());
+                sim.step(c);
+                let next = sim.snapshot();
+                if next.fault.is_none()
+                    && code.starts_with(next.output.as_str())
+                    && seen.insert(key(&next))
+                {
+                    queue.push_back((next, presses + 1));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn solve_from_matches_breadth_first_search() {
+        let layout = ChainLayout::default();
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+        for depth in 0..3 {
+            let mut solver = Solver::new(&layout, depth);
+            for _ in 0..50 {
+                // move the arms around without typing anything
+                let mut sim = Simulator::with_layout(&layout, depth);
+                let mut prefix = String::new();
+                for _ in 0..random(15) {
+                    let c = DIR_LABELS[random(5)];
+                    let before = sim.snapshot();
+                    sim.step(c);
+                    if sim.state().fault.is_some() || !sim.state().output.is_empty() {
+                        sim.restore(before);
+                    } else {
+                        prefix.push(c);
+                    }
+                }
+                let code = ["029A", "980A", "1A", "A", "7"][random(5)];
+                let arms: Vec<char> = (0..sim.pad_count())
+                    .map(|pad| sim.keypad(pad).label(sim.state().pads[pad].pos).unwrap())
+                    .collect();
+
+                let length = solver.length_from(&arms, code).unwrap();
+                let sequence = solver.solve_from(&arms, code).unwrap();
+                assert_eq!(sequence.len() as u128, length);
+                assert_eq!(
+                    solver.first_press(&arms, code).unwrap(),
+                    sequence.chars().next()
+                );
+                let start = sim.snapshot();
+                assert_eq!(
+                    bfs(&mut sim, start, code),
+                    Some(length),
+                    "depth {depth}, {prefix} then {code}"
+                );
+
+                let mut check = Simulator::with_layout(&layout, depth);
+                check.run(&prefix);
+                let state = check.run(&sequence);
+                assert!(state.fault.is_none());
+                assert_eq!(state.output, code, "{prefix} then {sequence}");
+            }
+        }
+    }
+}
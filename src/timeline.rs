@@ -0,0 +1,244 @@
+//! Random access to the chain state anywhere in a long line.
+//!
+//! Replaying a line from the first press on every cursor move is linear in
+//! the cursor position. A [`Timeline`] keeps the current state and a snapshot
+//! every [`CHECKPOINT_INTERVAL`] presses, so stepping is constant time and a
+//! jump replays at most one interval.
+
+use std::ops::Range;
+
+use crate::sim::{ChainState, Simulator};
+use crate::trace::Event;
+
+/// Number of presses between two checkpoints.
+pub const CHECKPOINT_INTERVAL: usize = 1024;
+
+/// Chain state without its output. The output of every position is a prefix
+/// of the output of any later position, so only its length is kept.
+struct Checkpoint {
+    state: ChainState,
+    output_len: usize,
+}
+
+pub struct Timeline {
+    sim: Simulator,
+    input: Vec<char>,
+    position: usize,
+    /// `checkpoints[i]` is the state after `i * CHECKPOINT_INTERVAL` presses.
+    checkpoints: Vec<Checkpoint>,
+    /// Output at the furthest position reached so far.
+    output: String,
+}
+
+impl Timeline {
+    /// Timeline over `input`, positioned before the first press.
+    pub fn new(mut sim: Simulator, input: &str) -> Self {
+        sim.reset();
+        let start = Checkpoint {
+            state: sim.snapshot(),
+            output_len: 0,
+        };
+        Self {
+            sim,
+            input: input.chars().collect(),
+            position: 0,
+            checkpoints: vec![start],
+            output: String::new(),
+        }
+    }
+
+    /// Number of presses in the line.
+    pub fn len(&self) -> usize {
+        self.input.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Number of presses fed into the chain.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn state(&self) -> &ChainState {
+        self.sim.state()
+    }
+
+    /// The simulator, holding the state and events of the last press.
+    pub fn simulator(&self) -> &Simulator {
+        &self.sim
+    }
+
+    /// Move to the state after the first `position` presses.
+    pub fn seek(&mut self, position: usize) -> &ChainState {
+        let target = position.min(self.input.len());
+        if target == self.position {
+            return self.sim.state();
+        }
+        if target == 0 {
+            self.sim.reset();
+            self.position = 0;
+            return self.sim.state();
+        }
+        let forward = target > self.position;
+        if !forward || target - self.position > CHECKPOINT_INTERVAL {
+            // end on a real press, so the events belong to the last press
+            let index = ((target - 1) / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+            let checkpoint_pos = index * CHECKPOINT_INTERVAL;
+            if !forward || self.position < checkpoint_pos {
+                let checkpoint = &self.checkpoints[index];
+                let mut state = checkpoint.state.clone();
+                state.output = self.output[..checkpoint.output_len].to_string();
+                self.sim.restore(state);
+                self.position = checkpoint_pos;
+            }
+        }
+        while self.position < target {
+            self.step_forward();
+        }
+        self.sim.state()
+    }
+
+    /// Closest position after the current one whose last press produced
+    /// events matching `pred`. The timeline stays where it was.
+    pub fn find_next<F: Fn(&[Event]) -> bool>(&mut self, pred: F) -> Option<usize> {
+        let start = self.position;
+        let mut found = None;
+        for position in start + 1..=self.input.len() {
+            self.seek(position);
+            if pred(self.sim.events()) {
+                found = Some(position);
+                break;
+            }
+        }
+        self.seek(start);
+        found
+    }
+
+    /// Closest position before the current one whose last press produced
+    /// events matching `pred`. Searches one checkpoint interval at a time,
+    /// so the timeline stays where it was.
+    pub fn find_prev<F: Fn(&[Event]) -> bool>(&mut self, pred: F) -> Option<usize> {
+        let start = self.position;
+        let mut found = None;
+        let mut end = start;
+        while found.is_none() && end > 1 {
+            let from = (end - 2) / CHECKPOINT_INTERVAL * CHECKPOINT_INTERVAL;
+            self.seek(from);
+            for position in from + 1..end {
+                self.seek(position);
+                if pred(self.sim.events()) {
+                    found = Some(position);
+                }
+            }
+            end = from + 1;
+        }
+        self.seek(start);
+        found
+    }
+
+    /// Events of the presses in `range`. The timeline stays where it was.
+    pub fn trace(&mut self, range: Range<usize>) -> Vec<Event> {
+        let start = self.position;
+        let mut events = vec![];
+        self.seek(range.start);
+        for position in range.start + 1..=range.end.min(self.input.len()) {
+            self.seek(position);
+            events.extend_from_slice(self.sim.events());
+        }
+        self.seek(start);
+        events
+    }
+
+    fn step_forward(&mut self) {
+        self.sim.step(self.input[self.position]);
+        self.position += 1;
+        let output = &self.sim.state().output;
+        if output.len() > self.output.len() {
+            self.output.push_str(&output[self.output.len()..]);
+        }
+        if self.position.is_multiple_of(CHECKPOINT_INTERVAL)
+            && self.position / CHECKPOINT_INTERVAL == self.checkpoints.len()
+        {
+            let current = self.sim.state();
+            let state = ChainState {
+                pads: current.pads.clone(),
+                output: String::new(),
+                steps: current.steps,
+                fault: current.fault.clone(),
+            };
+            let output_len = current.output.len();
+            self.checkpoints.push(Checkpoint { state, output_len });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypad::ChainLayout;
+    use crate::solver::Solver;
+
+    /// A line of a bit more than three checkpoint intervals that types
+    /// codes without a fault.
+    fn long_line() -> String {
+        let mut solver = Solver::new(&ChainLayout::default(), 2);
+        let codes = ["029A", "980A", "179A", "456A", "379A"];
+        let mut line = String::new();
+        while line.len() < 3 * CHECKPOINT_INTERVAL + 100 {
+            for code in codes {
+                line += &solver.solve(code).unwrap();
+            }
+        }
+        line
+    }
+
+    /// Positions on both sides of every checkpoint, visited back and forth.
+    fn positions(len: usize) -> Vec<usize> {
+        let mut positions = vec![0, len, 1];
+        for i in (1..=len / CHECKPOINT_INTERVAL).rev() {
+            let p = i * CHECKPOINT_INTERVAL;
+            positions.extend([p + 1, p, p - 1, p + 7, p - CHECKPOINT_INTERVAL / 2]);
+        }
+        positions.extend([len - 1, 2, len]);
+        positions
+    }
+
+    #[test]
+    fn seek_matches_a_fresh_run() {
+        let line = long_line();
+        let mut timeline = Timeline::new(Simulator::new(2), &line);
+        timeline.seek(line.len());
+        for position in positions(line.len()) {
+            let mut sim = Simulator::new(2);
+            sim.run(&line[..position]);
+            assert_eq!(timeline.seek(position), sim.state(), "at {position}");
+            assert_eq!(timeline.simulator().events(), sim.events(), "at {position}");
+        }
+    }
+
+    #[test]
+    fn find_next_and_prev_match_a_scan() {
+        let line = long_line();
+        let emitted: Vec<usize> = Simulator::new(2)
+            .trace(&line)
+            .iter()
+            .filter_map(|e| match e {
+                Event::Emitted { step, .. } => Some(step + 1),
+                _ => None,
+            })
+            .collect();
+        let is_output =
+            |events: &[Event]| events.iter().any(|e| matches!(e, Event::Emitted { .. }));
+        let mut timeline = Timeline::new(Simulator::new(2), &line);
+        for position in positions(line.len()) {
+            timeline.seek(position);
+            let next = emitted.iter().copied().find(|p| *p > position);
+            let prev = emitted.iter().copied().rev().find(|p| *p < position);
+            assert_eq!(timeline.find_next(is_output), next, "after {position}");
+            assert_eq!(timeline.find_prev(is_output), prev, "before {position}");
+            assert_eq!(timeline.position(), position);
+        }
+    }
+}
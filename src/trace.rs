@@ -0,0 +1,131 @@
+//! Typed record of everything that happens in the chain.
+//!
+//! The simulator emits one [`Event`] for every button press and arm move, so
+//! a whole line can be replayed, diffed against another run, or written out
+//! as JSON Lines for other tools.
+
+use std::io::{self, Write};
+
+use crate::sim::{FaultKind, Pos};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The arm of `pad` moved from one button to a neighbour.
+    Moved {
+        step: usize,
+        pad: usize,
+        from: Pos,
+        to: Pos,
+    },
+    /// The button `label` was pressed on `pad`, by hand on pad 0.
+    Pressed {
+        step: usize,
+        pad: usize,
+        label: char,
+    },
+    /// The num pad produced `label`.
+    Emitted { step: usize, label: char },
+    /// The robot at `pad` panicked trying to move from `from` to `to`.
+    Fault {
+        step: usize,
+        pad: usize,
+        kind: FaultKind,
+        from: Pos,
+        to: Pos,
+    },
+}
+
+impl Event {
+    /// Index of the manual press the event belongs to.
+    pub fn step(&self) -> usize {
+        match *self {
+            Event::Moved { step, .. }
+            | Event::Pressed { step, .. }
+            | Event::Emitted { step, .. }
+            | Event::Fault { step, .. } => step,
+        }
+    }
+
+    /// The event as a single line JSON object.
+    pub fn to_json(&self) -> String {
+        match *self {
+            Event::Moved {
+                step,
+                pad,
+                from,
+                to,
+            } => format!(
+                r#"{{"step":{step},"event":"moved","pad":{pad},"from":[{},{}],"to":[{},{}]}}"#,
+                from.0, from.1, to.0, to.1
+            ),
+            Event::Pressed { step, pad, label } => format!(
+                r#"{{"step":{step},"event":"pressed","pad":{pad},"label":{}}}"#,
+                json_char(label)
+            ),
+            Event::Emitted { step, label } => format!(
+                r#"{{"step":{step},"event":"emitted","label":{}}}"#,
+                json_char(label)
+            ),
+            Event::Fault {
+                step,
+                pad,
+                kind,
+                from,
+                to,
+            } => {
+                let reason = match kind {
+                    FaultKind::InvalidInput(c) => {
+                        format!(r#""invalid_input","input":{}"#, json_char(c))
+                    }
+                    FaultKind::OffGrid => r#""off_grid""#.to_string(),
+                    FaultKind::Gap => r#""gap""#.to_string(),
+                };
+                format!(
+                    r#"{{"step":{step},"event":"fault","pad":{pad},"reason":{reason},"from":[{},{}],"to":[{},{}]}}"#,
+                    from.0, from.1, to.0, to.1
+                )
+            }
+        }
+    }
+}
+
+/// Write `events` as JSON Lines, one object per line.
+pub fn write_jsonl<W: Write>(events: &[Event], mut out: W) -> io::Result<()> {
+    for e in events {
+        writeln!(out, "{}", e.to_json())?;
+    }
+    Ok(())
+}
+
+/// Buttons pressed on each of the `pad_count` pads, the manual presses first
+/// and the num pad output last.
+pub fn presses_by_pad(events: &[Event], pad_count: usize) -> Vec<String> {
+    let mut presses = vec![String::new(); pad_count];
+    for e in events {
+        if let Event::Pressed { pad, label, .. } = e {
+            presses[*pad].push(*label);
+        }
+    }
+    presses
+}
+
+/// `c` as a quoted JSON string.
+pub fn json_char(c: char) -> String {
+    json_string(&c.to_string())
+}
+
+/// `s` as a quoted JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
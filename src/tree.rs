@@ -0,0 +1,183 @@
+//! Which presses on the pads closer to the manual pad caused a press.
+//!
+//! A press on pad `k` is caused by the presses on pad `k - 1` since the
+//! previous press on pad `k`: the moves of the arm and the final `A`. Going
+//! down level by level ends at a range of manual presses.
+
+use std::io::{self, Write};
+
+use crate::keypad::display_label;
+use crate::trace::Event;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub pad: usize,
+    pub label: char,
+    /// Manual press that made the press happen, counting from 0.
+    pub step: usize,
+    /// First manual press of the range this press expands to.
+    pub first_step: usize,
+    /// Indices into [`Tree::nodes`] of the presses on the pad below.
+    pub children: Vec<usize>,
+}
+
+/// Expansion of one press, the press itself at index 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// Expansion of the press on `pad` at manual press `step`, from the
+    /// events of a chain of `pad_count` pads up to `step`. They may start at
+    /// the previous press on `pad` instead of the first manual press. `None`
+    /// if `pad` pressed nothing at `step`.
+    pub fn build(events: &[Event], pad_count: usize, pad: usize, step: usize) -> Option<Tree> {
+        let mut presses: Vec<Vec<(usize, char)>> = vec![vec![]; pad_count];
+        for e in events {
+            if let Event::Pressed { step, pad, label } = *e {
+                presses[pad].push((step, label));
+            }
+        }
+        let index = presses.get(pad)?.iter().position(|(s, _)| *s == step)?;
+        let mut tree = Tree { nodes: vec![] };
+        tree.add(&presses, pad, index);
+        Some(tree)
+    }
+
+    /// Add press `index` of `pad` and everything below it, returning its
+    /// index in [`Tree::nodes`].
+    fn add(&mut self, presses: &[Vec<(usize, char)>], pad: usize, index: usize) -> usize {
+        let (step, label) = presses[pad][index];
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            pad,
+            label,
+            step,
+            first_step: step,
+            children: vec![],
+        });
+        if pad == 0 {
+            return id;
+        }
+        let below = &presses[pad - 1];
+        let start = match index {
+            0 => 0,
+            i => below.partition_point(|(s, _)| *s <= presses[pad][i - 1].0),
+        };
+        let end = below.partition_point(|(s, _)| *s <= step);
+        let children: Vec<usize> = (start..end)
+            .map(|i| self.add(presses, pad - 1, i))
+            .collect();
+        if let Some(first) = children.first() {
+            self.nodes[id].first_step = self.nodes[*first].first_step;
+        }
+        self.nodes[id].children = children;
+        id
+    }
+
+    /// Write the tree as a Graphviz digraph, `name` naming pad `k`.
+    pub fn write_dot<W: Write, F: Fn(usize) -> String>(
+        &self,
+        pad_count: usize,
+        name: F,
+        mut out: W,
+    ) -> io::Result<()> {
+        writeln!(out, "digraph expansion {{")?;
+        writeln!(out, "  node [shape=box];")?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            let label = if node.pad < pad_count - 1 {
+                display_label(node.label)
+            } else {
+                node.label
+            };
+            let label = match label {
+                '"' | '\\' => format!("\\{label}"),
+                c => c.to_string(),
+            };
+            let range = if node.first_step == node.step {
+                format!("press {}", node.step + 1)
+            } else {
+                format!("presses {}-{}", node.first_step + 1, node.step + 1)
+            };
+            writeln!(
+                out,
+                "  n{id} [label=\"{}\\n{label}\\n{range}\"];",
+                name(node.pad)
+            )?;
+            for child in &node.children {
+                writeln!(out, "  n{id} -> n{child};")?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypad::ChainLayout;
+    use crate::sim::Simulator;
+    use crate::solver::Solver;
+    use crate::trace::presses_by_pad;
+    use std::collections::BTreeMap;
+    use std::ops::RangeInclusive;
+
+    /// Labels of the nodes on `pad`, in step order.
+    fn labels(tree: &Tree, pad: usize) -> String {
+        tree.nodes
+            .iter()
+            .filter(|n| n.pad == pad)
+            .map(|n| (n.step, n.label))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect()
+    }
+
+    /// Events of the manual presses in `steps`.
+    fn between(events: &[Event], steps: RangeInclusive<usize>) -> Vec<Event> {
+        events
+            .iter()
+            .filter(|e| steps.contains(&e.step()))
+            .cloned()
+            .collect()
+    }
+
+    /// The trees of all presses on a pad, built from the events up to the
+    /// press or from the previous press on, hold every press below it once.
+    #[test]
+    fn trees_of_a_pad_cover_the_presses_below() {
+        let depth = 2;
+        let line = Solver::new(&ChainLayout::default(), depth)
+            .solve("029A")
+            .unwrap();
+        let events = Simulator::new(depth).trace(&line);
+        let pad_count = depth + 2;
+        assert_eq!(presses_by_pad(&events, pad_count)[pad_count - 1], "029A");
+        for pad in 1..pad_count {
+            let steps: Vec<usize> = events
+                .iter()
+                .filter_map(|e| match *e {
+                    Event::Pressed { step, pad: p, .. } if p == pad => Some(step),
+                    _ => None,
+                })
+                .collect();
+            let mut below = vec![String::new(); pad + 1];
+            for (i, &step) in steps.iter().enumerate() {
+                let tree = Tree::build(&between(&events, 0..=step), pad_count, pad, step).unwrap();
+                if i > 0 {
+                    let trimmed = between(&events, steps[i - 1]..=step);
+                    let trimmed = Tree::build(&trimmed, pad_count, pad, step);
+                    assert_eq!(trimmed.as_ref(), Some(&tree), "pad {pad} press {i}");
+                }
+                for (p, below) in below.iter_mut().enumerate() {
+                    *below += &labels(&tree, p);
+                }
+            }
+            let last = *steps.last().unwrap();
+            let expected = presses_by_pad(&between(&events, 0..=last), pad_count);
+            assert_eq!(below, expected[..=pad], "pad {pad}");
+        }
+        assert_eq!(Tree::build(&events, pad_count, pad_count - 1, 0), None);
+    }
+}